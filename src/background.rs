@@ -1,28 +1,24 @@
 use std::{
-    error::Error,
     path::PathBuf,
     process::Command,
-    sync::{
-        Mutex,
-        Arc
-    }
 };
 use log::{
     error,
-    info,
-    warn
+    info
 };
 use crate::{
     loge,
-    logi,
-    logw
+    logi
 };
 
-use crate::RunningTask;
+use crate::{
+    RunningTask,
+    launcher::Launcher
+};
 
-use crate::error::error_with_message as display_error_with_message;
+use crate::error::MediatimerError;
 
-pub fn make() -> Result<(), Box<dyn Error>> {
+pub fn make() -> Result<(), MediatimerError> {
     logi!("Making background");
     let username = whoami::username();
     let env_dir_path: PathBuf =["/home/", &username, ".mediatimer_config/black.mp4"].iter().collect();
@@ -39,35 +35,36 @@ pub fn make() -> Result<(), Box<dyn Error>> {
             .arg("-t")
             .arg("2")
             .arg(path_str)
-            .output()?;
+            .output()
+            .map_err(|e| MediatimerError::Spawn(format!("Failed to run ffmpeg to make the background: {}", e)))?;
     } else {
         loge!("Failed to convert background path to str"); 
-        display_error_with_message("Failed to convert background path to str");
+        return Err(MediatimerError::Config(String::from("Failed to convert background path to str")));
     };
     Ok(())
 }
 
-pub fn run(task_list: Arc<Mutex<Vec<RunningTask>>>) -> Result<(), Box<dyn Error>> {
+/// Starts the looping black background that is shown whenever no task is playing
+pub fn run(launcher: &dyn Launcher) -> Result<RunningTask, MediatimerError> {
     logi!("Attempting to run background");
     let username = whoami::username();
     let env_dir_path: PathBuf =["/home/", &username, ".mediatimer_config/black.mp4"].iter().collect();
 
     if let Some(path_str) = env_dir_path.to_str() {
-        let child = Command::new("ffplay")
+        let child = launcher.spawn(Command::new("ffplay")
             .arg("-hide_banner")
             .arg("-loglevel")
             .arg("error")
             .arg("-fs")
             .arg("-loop")
             .arg("-1")
-            .arg(path_str)
-            .spawn()?;
+            .arg(path_str))
+            .map_err(|e| MediatimerError::Spawn(format!("Failed to start the background: {}", e)))?;
 
-        let running_task = RunningTask::new(child, true);
-        task_list.lock().unwrap().push(running_task)
+        logi!(MT_EVENT = "background", MT_PID = child.id(); "Background started with pid {}", child.id());
+        Ok(RunningTask::new(child))
     } else {
         loge!("Failed to convert background path to str"); 
-        display_error_with_message("Failed to convert background path to str");
-    };
-    Ok(())
+        Err(MediatimerError::Config(String::from("Failed to convert background path to str")))
+    }
 }
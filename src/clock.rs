@@ -0,0 +1,191 @@
+use std::{
+    cell::Cell,
+    thread,
+    time::{
+        Duration,
+        Instant
+    },
+};
+
+use chrono::{
+    DateTime,
+    TimeDelta,
+    Utc
+};
+
+/// The longest the system clock sleeps in one go. After every sleep the wall clock is
+/// compared against the monotonic clock so that clock changes are noticed within this period.
+const MAX_SLEEP: Duration = Duration::from_secs(30);
+
+/// The amount the wall clock may drift from the monotonic clock during a sleep before it is
+/// treated as a clock jump.
+const JUMP_TOLERANCE_MS: i64 = 2000;
+
+/// The reason a clock woke up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wake {
+    Due,
+    ClockJump(TimeDelta),
+}
+
+/// The source of time for the scheduler. The scheduler never reads the time or sleeps
+/// other than through this, so tests can run a schedule against simulated time.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Sleeps until the wall clock reaches `at`, returning early if the wall clock is
+    /// changed while sleeping or since the last sleep ended.
+    fn sleep_until(&self, at: DateTime<Utc>) -> Wake;
+}
+
+impl<C: Clock> Clock for &C {
+    fn now(&self) -> DateTime<Utc> {
+        (*self).now()
+    }
+
+    fn sleep_until(&self, at: DateTime<Utc>) -> Wake {
+        (*self).sleep_until(at)
+    }
+}
+
+/// The real wall clock
+#[derive(Debug, Default)]
+pub struct SystemClock {
+    /// The wall and monotonic time when the last sleep ended
+    woke: Cell<Option<(DateTime<Utc>, Instant)>>,
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    /// The sleep is broken into chunks of at most `MAX_SLEEP`, comparing the wall clock with
+    /// the monotonic clock after each one. The first comparison is with the end of the last
+    /// sleep, so a change made while the events were being handled is noticed too.
+    fn sleep_until(&self, at: DateTime<Utc>) -> Wake {
+        let mut since = self.woke.get().unwrap_or_else(|| (Utc::now(), Instant::now()));
+        let wake = loop {
+            let (wall, mono) = (Utc::now(), Instant::now());
+            let mono_elapsed = TimeDelta::from_std(mono - since.1).unwrap_or(TimeDelta::zero());
+            let drift = (wall - since.0) - mono_elapsed;
+            if drift.num_milliseconds().abs() > JUMP_TOLERANCE_MS {
+                break Wake::ClockJump(drift);
+            }
+            since = (wall, mono);
+
+            crate::notify::alive();
+            let remaining = at - wall;
+            if remaining <= TimeDelta::zero() {
+                break Wake::Due;
+            }
+            thread::sleep(remaining.to_std().unwrap_or(Duration::ZERO).min(MAX_SLEEP));
+        };
+        self.woke.set(Some((Utc::now(), Instant::now())));
+        wake
+    }
+}
+
+/// A clock for tests that moves instantly to whatever time is slept until. Clock jumps can
+/// be scheduled to happen when the clock passes a given instant.
+/// A clock for tests that moves instantly to whatever time is slept until. Clock jumps can
+/// be scheduled to happen when the clock passes a given instant.
+#[cfg(test)]
+pub mod simulated {
+    use std::{
+        cell::RefCell,
+        collections::VecDeque,
+    };
+
+    use super::*;
+
+    pub struct SimulatedClock {
+        now: Cell<DateTime<Utc>>,
+        /// (when, jump to) pairs, in order
+        jumps: RefCell<VecDeque<(DateTime<Utc>, DateTime<Utc>)>>,
+        /// A jump made by `advance`, reported by the next sleep
+        jumped: Cell<Option<TimeDelta>>,
+    }
+
+    impl SimulatedClock {
+        pub fn new(now: DateTime<Utc>) -> SimulatedClock {
+            SimulatedClock {
+                now: Cell::new(now),
+                jumps: RefCell::new(VecDeque::new()),
+                jumped: Cell::new(None),
+            }
+        }
+
+        /// Sets the wall clock to `to` once it reaches `at`, as an NTP correction would
+        pub fn jump_at(&self, at: DateTime<Utc>, to: DateTime<Utc>) {
+            self.jumps.borrow_mut().push_back((at, to));
+        }
+
+        /// Moves the clock on by `by` without sleeping, as handling an event takes time
+        pub fn advance(&self, by: TimeDelta) {
+            let until = self.now.get() + by;
+            let mut jumps = self.jumps.borrow_mut();
+            if let Some((when, to)) = jumps.front().copied()
+                && when <= until {
+                jumps.pop_front();
+                self.now.set(to + (until - when));
+                self.jumped.set(Some(to - when));
+            } else {
+                self.now.set(until);
+            }
+        }
+    }
+
+    impl Clock for SimulatedClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.now.get()
+        }
+
+        fn sleep_until(&self, at: DateTime<Utc>) -> Wake {
+            if let Some(drift) = self.jumped.take() {
+                return Wake::ClockJump(drift);
+            }
+            let mut jumps = self.jumps.borrow_mut();
+            if let Some((when, to)) = jumps.front().copied()
+                && when < at {
+                jumps.pop_front();
+                self.now.set(to);
+                return Wake::ClockJump(to - when);
+            }
+            self.now.set(self.now.get().max(at));
+            Wake::Due
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::simulated::SimulatedClock;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_simulated_clock() {
+        let start = Utc.with_ymd_and_hms(2025, 6, 2, 0, 0, 0).unwrap();
+        let clock = SimulatedClock::new(start);
+        clock.jump_at(start + TimeDelta::hours(2), start + TimeDelta::hours(5));
+
+        assert_eq!(clock.sleep_until(start + TimeDelta::hours(1)), Wake::Due);
+        assert_eq!(clock.now(), start + TimeDelta::hours(1));
+
+        // the jump happens before the next wake up time
+        assert_eq!(clock.sleep_until(start + TimeDelta::hours(3)), Wake::ClockJump(TimeDelta::hours(3)));
+        assert_eq!(clock.now(), start + TimeDelta::hours(5));
+
+        // sleeping until a time already passed returns straight away
+        assert_eq!(clock.sleep_until(start + TimeDelta::hours(4)), Wake::Due);
+        assert_eq!(clock.now(), start + TimeDelta::hours(5));
+
+        // a jump while the clock is advanced is reported by the next sleep
+        clock.jump_at(start + TimeDelta::hours(6), start + TimeDelta::hours(8));
+        clock.advance(TimeDelta::hours(2));
+        assert_eq!(clock.now(), start + TimeDelta::hours(9));
+        assert_eq!(clock.sleep_until(start + TimeDelta::hours(10)), Wake::ClockJump(TimeDelta::hours(2)));
+        assert_eq!(clock.now(), start + TimeDelta::hours(9));
+    }
+}
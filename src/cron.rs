@@ -0,0 +1,237 @@
+use std::fmt;
+
+use chrono::{
+    Datelike,
+    NaiveDate,
+    NaiveTime
+};
+
+use crate::error::MediatimerError;
+
+/// A five field cron expression: minute, hour, day of month, month and day of week.
+///
+/// Each field accepts `*`, single values, ranges (`10-16`), steps (`*/15`, `10-16/2`) and
+/// comma separated lists of these. Months and days of the week may also be given by their
+/// three letter English names. A day of the week may be followed by `#n` to match only the
+/// nth such day of the month, so `0 10 * * sun#1` runs at 10:00 on the first Sunday of every
+/// month. As with standard cron, when both the day of month and the day of week are
+/// restricted a date matches if either of them does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    /// (day of week, n) pairs from `#n` entries
+    nth_days_of_week: Vec<(u32, u32)>,
+    day_of_month_restricted: bool,
+    day_of_week_restricted: bool,
+}
+
+const MONTH_NAMES: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+fn cron_error(expression: &str, reason: &str) -> MediatimerError {
+    MediatimerError::Schedule(format!("Cron expression '{}' is incorrect: {}", expression, reason))
+}
+
+/// Parses a single value, which may be a name from `names` (numbered from `first`)
+fn parse_value(value: &str, names: &[&str], first: u32) -> Option<u32> {
+    let value = value.to_lowercase();
+    if let Some(index) = names.iter().position(|n| *n == value) {
+        return Some(index as u32 + first);
+    }
+    value.parse::<u32>().ok()
+}
+
+/// Parses one field into a table of allowed values, indexed from zero up to `max`
+fn parse_field(expression: &str, field: &str, min: u32, max: u32, names: &[&str]) -> Result<Vec<bool>, MediatimerError> {
+    let mut allowed = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step.parse::<u32>().map_err(|_| cron_error(expression, "step is not a number"))?;
+                if step == 0 {
+                    return Err(cron_error(expression, "step must be greater than zero"));
+                }
+                (range, step)
+            },
+            None => (part, 1)
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            let start = parse_value(start, names, min).ok_or_else(|| cron_error(expression, "range start not recognised"))?;
+            let end = parse_value(end, names, min).ok_or_else(|| cron_error(expression, "range end not recognised"))?;
+            (start, end)
+        } else {
+            let value = parse_value(range, names, min).ok_or_else(|| cron_error(expression, "value not recognised"))?;
+            // a single value with a step runs from that value to the end of the range
+            if step > 1 { (value, max) } else { (value, value) }
+        };
+        if start < min || end > max || start > end {
+            return Err(cron_error(expression, "value out of range"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+    Ok(allowed)
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<CronSchedule, MediatimerError> {
+        let expression = expression.trim();
+        let fields = expression.split_whitespace().collect::<Vec<&str>>();
+        if fields.len() != 5 {
+            return Err(cron_error(expression, "expected five fields"));
+        }
+
+        // pull out any nth day of week entries before parsing the rest of the field
+        let mut nth_days_of_week = Vec::new();
+        let mut plain_days = Vec::new();
+        for part in fields[4].split(',') {
+            if let Some((day, n)) = part.split_once('#') {
+                let day = parse_value(day, &DAY_NAMES, 0).ok_or_else(|| cron_error(expression, "day of week not recognised"))?;
+                let n = n.parse::<u32>().map_err(|_| cron_error(expression, "nth day of week is not a number"))?;
+                if day > 7 || !(1..=5).contains(&n) {
+                    return Err(cron_error(expression, "nth day of week out of range"));
+                }
+                nth_days_of_week.push((day % 7, n));
+            } else {
+                plain_days.push(part);
+            }
+        }
+        let mut days_of_week = if plain_days.is_empty() {
+            vec![false; 8]
+        } else {
+            parse_field(expression, &plain_days.join(","), 0, 7, &DAY_NAMES)?
+        };
+        // 7 is an alias for Sunday
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+
+        Ok(CronSchedule {
+            expression: expression.to_string(),
+            minutes: parse_field(expression, fields[0], 0, 59, &[])?,
+            hours: parse_field(expression, fields[1], 0, 23, &[])?,
+            days_of_month: parse_field(expression, fields[2], 1, 31, &[])?,
+            months: parse_field(expression, fields[3], 1, 12, &MONTH_NAMES)?,
+            days_of_week,
+            nth_days_of_week,
+            day_of_month_restricted: fields[2] != "*",
+            day_of_week_restricted: fields[4] != "*",
+        })
+    }
+
+    /// Returns true if the expression runs at some time on the given date
+    pub fn matches_date(&self, date: NaiveDate) -> bool {
+        if !self.months[date.month() as usize] {
+            return false;
+        }
+        let weekday = date.weekday().num_days_from_sunday();
+        let day_of_month = self.days_of_month[date.day() as usize];
+        let day_of_week = self.days_of_week[weekday as usize]
+            || self.nth_days_of_week.iter().any(|(day, n)| *day == weekday && (date.day() - 1) / 7 + 1 == *n);
+
+        match (self.day_of_month_restricted, self.day_of_week_restricted) {
+            (true, true) => day_of_month || day_of_week,
+            (true, false) => day_of_month,
+            (false, true) => day_of_week,
+            (false, false) => true
+        }
+    }
+
+    /// All the times of day the expression runs at, in order
+    pub fn times(&self) -> Vec<NaiveTime> {
+        let mut times = Vec::new();
+        for (hour, _) in self.hours.iter().enumerate().filter(|(_, h)| **h) {
+            for (minute, _) in self.minutes.iter().enumerate().filter(|(_, m)| **m) {
+                if let Some(time) = NaiveTime::from_hms_opt(hour as u32, minute as u32, 0) {
+                    times.push(time);
+                }
+            }
+        }
+        times
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+/// Parses a list of cron expressions separated by ';'
+pub fn parse_list(value: &str) -> Result<Vec<CronSchedule>, MediatimerError> {
+    value.split(';')
+        .map(|e| e.trim())
+        .filter(|e| !e.is_empty())
+        .map(CronSchedule::parse)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_every_fifteen_minutes() {
+        let cron = CronSchedule::parse("*/15 10-15 * * *").unwrap();
+        let times = cron.times();
+        assert_eq!(times.len(), 24);
+        assert_eq!(times[0], NaiveTime::from_hms_opt(10, 0, 0).unwrap());
+        assert_eq!(times[1], NaiveTime::from_hms_opt(10, 15, 0).unwrap());
+        assert_eq!(times[23], NaiveTime::from_hms_opt(15, 45, 0).unwrap());
+        assert!(cron.matches_date(date(2025, 6, 2)));
+    }
+
+    #[test]
+    fn test_first_sunday_of_month() {
+        let cron = CronSchedule::parse("0 10 * * sun#1").unwrap();
+        // 2025-06-01 is the first Sunday of June, 2025-06-08 is the second
+        assert!(cron.matches_date(date(2025, 6, 1)));
+        assert!(!cron.matches_date(date(2025, 6, 8)));
+        assert!(!cron.matches_date(date(2025, 6, 2)));
+        assert!(cron.matches_date(date(2025, 7, 6)));
+    }
+
+    #[test]
+    fn test_day_of_month_or_day_of_week() {
+        let cron = CronSchedule::parse("0 9 1 * mon").unwrap();
+        // the 1st of the month and every Monday both match
+        assert!(cron.matches_date(date(2025, 6, 1)));
+        assert!(cron.matches_date(date(2025, 6, 9)));
+        assert!(!cron.matches_date(date(2025, 6, 10)));
+    }
+
+    #[test]
+    fn test_names_and_sunday_alias() {
+        let cron = CronSchedule::parse("30 18 * dec 7").unwrap();
+        assert!(cron.matches_date(date(2025, 12, 7)));
+        assert!(!cron.matches_date(date(2025, 11, 30)));
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("0 10 * * sun#6").is_err());
+        assert!(CronSchedule::parse("0 16-10 * * *").is_err());
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let list = parse_list("0 10 * * 1-5; 0 12 * * 6,0").unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[1].to_string(), "0 12 * * 6,0");
+    }
+}
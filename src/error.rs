@@ -1,5 +1,39 @@
-use std::error::Error;
-use std::process;
+use std::{
+    env,
+    error::Error,
+    fmt,
+    fs,
+    io::{
+        self,
+        IsTerminal
+    },
+    num::{
+        ParseFloatError,
+        ParseIntError
+    },
+    path::{
+        Path,
+        PathBuf
+    },
+    process::Command,
+    time::Duration,
+};
+use qrcodegen::{
+    QrCode,
+    QrCodeEcc
+};
+
+use crate::{
+    Autoloop,
+    Model,
+    ProcType,
+    RunningTask,
+    Task,
+    config_path,
+    launcher::Launcher,
+    mount,
+    task_runner::spawn_child
+};
 use ratatui::{
     buffer::Buffer,
     crossterm::event::{
@@ -21,35 +55,463 @@ use ratatui::{
     }
 };
 
-#[allow(dead_code)]
-pub fn error() {
-    let mut terminal = ratatui::init();
-    let _error_widget = ErrorTerm::default().run(&mut terminal);
-    ratatui::restore();
-    process::exit(1);
+/// Everything that can stop mediatimer_init from playing. Each kind of error has a stable code
+/// that is shown on the error screen and written to the log, so a fault reported from site
+/// can be identified without the full message.
+#[derive(Debug)]
+pub enum MediatimerError {
+    /// The config file is missing or a setting could not be read (MT-100)
+    Config(String),
+    /// A storage device could not be found, matched or mounted (MT-200)
+    Mount(String),
+    /// The media on a storage device could not be identified (MT-300)
+    Probe(String),
+    /// A player or helper program could not be started or stopped (MT-400)
+    Spawn(String),
+    /// The schedule could not be read or built (MT-500)
+    Schedule(String),
+    /// Any other failure reading or writing files (MT-900)
+    Io(io::Error),
+    /// One of the errors above, with the setting, device or file it happened on
+    Detailed(Box<MediatimerError>, Context),
+}
+
+/// Where an error happened, so the error screen can point at what needs fixing
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Context {
+    /// The config key and the value it was set to
+    pub setting: Option<(String, String)>,
+    pub device: Option<String>,
+    pub path: Option<PathBuf>,
+}
+
+impl Context {
+    /// One line for each detail that is known, as shown on the error screen
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some((key, value)) = &self.setting {
+            lines.push(format!("Setting: {}={}", key, value));
+        }
+        if let Some(device) = &self.device {
+            lines.push(format!("Device: {}", device));
+        }
+        if let Some(path) = &self.path {
+            lines.push(format!("Path: {}", path.display()));
+        }
+        lines
+    }
+}
+
+impl MediatimerError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            MediatimerError::Config(_) => "MT-100",
+            MediatimerError::Mount(_) => "MT-200",
+            MediatimerError::Probe(_) => "MT-300",
+            MediatimerError::Spawn(_) => "MT-400",
+            MediatimerError::Schedule(_) => "MT-500",
+            MediatimerError::Io(_) => "MT-900",
+            MediatimerError::Detailed(error, _) => error.code()
+        }
+    }
+
+    /// The message without the code, as shown to the user
+    pub fn message(&self) -> String {
+        match self {
+            MediatimerError::Config(message)
+            | MediatimerError::Mount(message)
+            | MediatimerError::Probe(message)
+            | MediatimerError::Spawn(message)
+            | MediatimerError::Schedule(message) => message.clone(),
+            MediatimerError::Io(e) => e.to_string(),
+            MediatimerError::Detailed(error, _) => error.message()
+        }
+    }
+
+    /// The setting, device and file the error happened on, where known
+    pub fn context(&self) -> Context {
+        match self {
+            MediatimerError::Detailed(_, context) => context.clone(),
+            _ => Context::default()
+        }
+    }
+
+    /// Adds to the context of the error. Details already set are kept, as they were added
+    /// closer to where the error happened.
+    fn detail(self, add: impl FnOnce(&mut Context)) -> MediatimerError {
+        let (error, mut context) = match self {
+            MediatimerError::Detailed(error, context) => (error, context),
+            error => (Box::new(error), Context::default())
+        };
+        add(&mut context);
+        MediatimerError::Detailed(error, context)
+    }
+
+    /// Records the config key and value that caused the error
+    pub fn with_setting(self, key: &str, value: &str) -> MediatimerError {
+        self.detail(|c| { c.setting.get_or_insert_with(|| (key.to_string(), value.to_string())); })
+    }
+
+    /// Records the storage device the error happened on
+    pub fn with_device(self, device: &str) -> MediatimerError {
+        self.detail(|c| { c.device.get_or_insert_with(|| device.to_string()); })
+    }
+
+    /// Records the file or directory the error happened on
+    pub fn with_path(self, path: &Path) -> MediatimerError {
+        self.detail(|c| { c.path.get_or_insert_with(|| path.to_path_buf()); })
+    }
+
+    /// The error without its context
+    fn root(&self) -> &MediatimerError {
+        match self {
+            MediatimerError::Detailed(error, _) => error.root(),
+            error => error
+        }
+    }
+
+    /// The steps to fix this error, most likely first. Steps naming the setting, device or
+    /// file involved come before the general steps for the kind of error.
+    pub fn remediation(&self) -> Vec<String> {
+        let context = self.context();
+        let config = config_path();
+        let mut steps = Vec::new();
+        match (self.root(), &context) {
+            (MediatimerError::Config(_) | MediatimerError::Schedule(_), Context { setting: Some((key, _)), .. }) => {
+                steps.push(format!("Correct {} in the config file at {}.", key, config.display()));
+            },
+            (MediatimerError::Mount(_), Context { device: Some(device), .. }) => {
+                steps.push(format!("Check {} can be read on another computer.", device));
+            },
+            (MediatimerError::Probe(_), Context { path: Some(path), .. }) => {
+                steps.push(format!("Check {} plays on another computer.", path.display()));
+            },
+            _ => {}
+        }
+        match self.root() {
+            MediatimerError::Config(_) => {
+                steps.push(String::from("Run 'mediatimer' in a terminal to set up the program."));
+                steps.push(format!("Or edit the config file at {}.", config.display()));
+            },
+            MediatimerError::Mount(_) => {
+                steps.push(String::from("Check the USB storage device is plugged in."));
+                steps.push(String::from("If the storage device was replaced, run 'mediatimer' to choose the file again."));
+            },
+            MediatimerError::Probe(_) => {
+                steps.push(String::from("Check the media file on the storage device plays on another computer."));
+                steps.push(String::from("Put a single media file in the autoplay folder, or several images for a slideshow."));
+            },
+            MediatimerError::Spawn(_) => {
+                steps.push(String::from("Check ffplay, feh and chromium are installed."));
+                steps.push(String::from("Restart the device."));
+            },
+            MediatimerError::Schedule(_) => {
+                steps.push(String::from("Check the schedule times in 'mediatimer'. Times are written as HH:MM:SS-HH:MM:SS."));
+                steps.push(format!("Or edit the MT_<DAY> settings in the config file at {}.", config.display()));
+            },
+            MediatimerError::Io(_) | MediatimerError::Detailed(..) => {
+                steps.push(String::from("Check the storage is not full or read only."));
+                steps.push(String::from("Restart the device."));
+            }
+        }
+        steps.dedup();
+        steps
+    }
+}
+
+impl fmt::Display for MediatimerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())?;
+        let context = self.context().lines();
+        if !context.is_empty() {
+            write!(f, " ({})", context.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for MediatimerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MediatimerError::Io(e) => Some(e),
+            MediatimerError::Detailed(error, _) => error.source(),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for MediatimerError {
+    fn from(e: io::Error) -> Self {
+        MediatimerError::Io(e)
+    }
+}
+
+impl From<ParseIntError> for MediatimerError {
+    fn from(e: ParseIntError) -> Self {
+        MediatimerError::Config(format!("Number incorrectly formatted: {}", e))
+    }
+}
+
+impl From<ParseFloatError> for MediatimerError {
+    fn from(e: ParseFloatError) -> Self {
+        MediatimerError::Config(format!("Number incorrectly formatted: {}", e))
+    }
+}
+
+impl From<chrono::ParseError> for MediatimerError {
+    fn from(e: chrono::ParseError) -> Self {
+        MediatimerError::Schedule(format!("Time incorrectly formatted: {}", e))
+    }
+}
+
+impl From<regex::Error> for MediatimerError {
+    fn from(e: regex::Error) -> Self {
+        MediatimerError::Config(format!("Pattern could not be built: {}", e))
+    }
 }
 
-pub fn error_with_message(message: &str) {
+/// How long a headless device shows the error card before trying to start again
+pub const HEADLESS_RETRY: Duration = Duration::from_secs(60);
+
+/// The page the QR code on the error card links to. The error code is added as a query so
+/// support can see which fault was scanned.
+const SUPPORT_URL: &str = "https://considerate.digital/mediatimer/support";
+
+/// The longest line of the message on the error card, in characters. This keeps the text clear
+/// of the QR code on the right of the card.
+const CARD_LINE_WIDTH: usize = 50;
+
+/// The most lines of the message drawn on the error card
+const CARD_MAX_LINES: usize = 8;
+
+/// How an error the device cannot carry on from is reported
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    /// Shown in the terminal until a key is pressed, then the program exits
+    Interactive,
+    /// Drawn full screen and start up is retried, for unattended sites without a keyboard
+    Headless,
+}
+
+impl Policy {
+    /// Headless when `MT_HEADLESS=true` is set in the service environment or the config file,
+    /// or when there is no terminal to show the error screen in
+    pub fn current() -> Policy {
+        Policy::from_env(env::var("MT_HEADLESS").ok().as_deref(), io::stdin().is_terminal())
+    }
+
+    fn from_env(headless: Option<&str>, terminal: bool) -> Policy {
+        if headless == Some("true") || !terminal {
+            Policy::Headless
+        } else {
+            Policy::Interactive
+        }
+    }
+}
+
+/// Breaks the message into lines of at most `width` characters, keeping words whole
+fn wrap(message: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in message.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + word.chars().count() + 1 > width {
+            lines.push(line);
+            line = String::new();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// The QR code for the support page as a binary PGM image, one pixel per module with a
+/// four module quiet zone. ffmpeg reads PGM directly, so no image library is needed.
+fn qr_pgm(url: &str) -> Result<Vec<u8>, MediatimerError> {
+    let qr = QrCode::encode_text(url, QrCodeEcc::Medium)
+        .map_err(|e| MediatimerError::Config(format!("Support URL could not be encoded as a QR code: {}", e)))?;
+    let border = 4;
+    let size = qr.size() + border * 2;
+    let mut pgm = format!("P5\n{} {}\n255\n", size, size).into_bytes();
+    for y in 0..size {
+        for x in 0..size {
+            // modules outside the code are light, which get_module already returns
+            pgm.push(if qr.get_module(x - border, y - border) { 0 } else { 255 });
+        }
+    }
+    Ok(pgm)
+}
+
+/// The message drawn on the error card, followed by the setting, device or file involved,
+/// wrapped to fit beside the QR code
+fn card_message(error: &MediatimerError) -> String {
+    let mut lines = wrap(&error.message(), CARD_LINE_WIDTH);
+    for line in error.context().lines() {
+        lines.extend(wrap(&line, CARD_LINE_WIDTH));
+    }
+    if lines.len() > CARD_MAX_LINES {
+        lines.truncate(CARD_MAX_LINES);
+        lines[CARD_MAX_LINES - 1].push_str(" ...");
+    }
+    lines.join("\n")
+}
+
+/// Renders the error card: the error code, message and first steps to fix it on the left and a QR code to the
+/// support page on the right. The text is drawn by ffmpeg from text files so that nothing in
+/// the message needs escaping in the filter.
+fn make_error_card(error: &MediatimerError) -> Result<PathBuf, MediatimerError> {
+    let username = whoami::username();
+    let dir: PathBuf = ["/home/", &username, ".mediatimer_config"].iter().collect();
+    let code_path = dir.join("error_code.txt");
+    let message_path = dir.join("error_message.txt");
+    let hint_path = dir.join("error_hint.txt");
+    let qr_path = dir.join("error_qr.pgm");
+    let card_path = dir.join("error.png");
+
+    fs::write(&code_path, format!("Error {}, trying again every {} seconds", error.code(), HEADLESS_RETRY.as_secs()))?;
+    fs::write(&message_path, card_message(error))?;
+    let hint = error.remediation().into_iter().take(2).collect::<Vec<String>>().join(" ");
+    fs::write(&hint_path, wrap(&hint, CARD_LINE_WIDTH + 10).join("\n"))?;
+    fs::write(&qr_path, qr_pgm(&format!("{}?code={}", SUPPORT_URL, error.code()))?)?;
+
+    let filter = format!(
+        "[1:v]scale=400:400:flags=neighbor[qr];\
+         [0:v]drawtext=text=Media Timer could not be started:fontcolor=white:fontsize=64:x=120:y=120,\
+         drawtext=textfile={code}:fontcolor=white:fontsize=40:x=120:y=230,\
+         drawtext=textfile={message}:fontcolor=white:fontsize=40:line_spacing=14:x=120:y=340,\
+         drawtext=textfile={hint}:fontcolor=0xffd0d0:fontsize=34:line_spacing=12:x=120:y=h-240,\
+         drawtext=text=Scan for help:fontcolor=white:fontsize=34:x=w-320-text_w/2:y=h/2+230[card];\
+         [card][qr]overlay=x=W-520:y=(H-h)/2",
+        code = code_path.display(),
+        message = message_path.display(),
+        hint = hint_path.display()
+    );
+
+    let output = Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-f")
+        .arg("lavfi")
+        .arg("-y")
+        .arg("-i")
+        .arg("color=c=0x8b0000:s=1920x1080")
+        .arg("-i")
+        .arg(&qr_path)
+        .arg("-filter_complex")
+        .arg(filter)
+        .arg("-frames:v")
+        .arg("1")
+        .arg(&card_path)
+        .output()
+        .map_err(|e| MediatimerError::Spawn(format!("Failed to run ffmpeg to draw the error card: {}", e)))?;
+    if !output.status.success() {
+        return Err(MediatimerError::Spawn(format!("ffmpeg could not draw the error card: {}", String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    Ok(card_path)
+}
+
+/// Draws the error card and shows it with the same player as an image task, so no terminal
+/// is needed. The player is returned so it can be closed before start up is tried again.
+pub fn display_error_card(launcher: &dyn Launcher, error: &MediatimerError) -> Result<RunningTask, MediatimerError> {
+    let card = make_error_card(error)?;
+    let task = Task::new(Model::Pro, ProcType::Image, Autoloop::No, card, 0, String::new());
+    spawn_child(launcher, &task, "0")
+        .map_err(|e| MediatimerError::Spawn(format!("Failed to show the error card: {}", e)))
+}
+
+/// Shows the error on the terminal until q is pressed. This only reports the error; the
+/// caller decides whether to carry on.
+pub fn display_error(error: &MediatimerError) {
     let mut terminal = ratatui::init();
-    let _error_widget = ErrorTerm::new(message).run(&mut terminal);
+    let _error_widget = ErrorTerm::new(error).run(&mut terminal);
     ratatui::restore();
-    panic!("Error: {}", &message);
 }
 
-#[derive(Default)]
+/// What the device could see when the error happened, shown on the diagnostics page
+struct Diagnostics {
+    drives: Result<Vec<String>, String>,
+    config_path: PathBuf,
+    /// The settings in the config file, or None if it could not be read
+    config: Option<Vec<String>>,
+}
+
+impl Diagnostics {
+    fn collect() -> Diagnostics {
+        let config_path = config_path();
+        Diagnostics {
+            drives: mount::describe_drives().map_err(|e| e.to_string()),
+            config: fs::read_to_string(&config_path).ok().map(|c| config_lines(&c)),
+            config_path,
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![String::from("Storage devices:")];
+        match &self.drives {
+            Ok(drives) if drives.is_empty() => lines.push(String::from("  None found")),
+            Ok(drives) => lines.extend(drives.iter().map(|d| format!("  {}", d))),
+            Err(e) => lines.push(format!("  Could not be listed: {}", e))
+        }
+        lines.push(String::new());
+        lines.push(format!("Config file {}:", self.config_path.display()));
+        match &self.config {
+            Some(config) if config.is_empty() => lines.push(String::from("  No settings")),
+            Some(config) => lines.extend(config.iter().map(|c| format!("  {}", c))),
+            None => lines.push(String::from("  Not found"))
+        }
+        lines.push(String::new());
+        lines.push(String::from("Press d to go back, q to quit."));
+        lines
+    }
+}
+
+/// The settings in a config file, leaving out blank lines and comments
+fn config_lines(contents: &str) -> Vec<String> {
+    contents.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Page {
+    Error,
+    Diagnostics
+}
+
 struct ErrorTerm {
     should_exit: bool,
-    message: String
+    page: Page,
+    code: &'static str,
+    message: String,
+    context: Vec<String>,
+    remediation: Vec<String>,
+    config_path: PathBuf,
+    /// Collected when the diagnostics page is first opened
+    diagnostics: Option<Diagnostics>,
 }
 
 impl ErrorTerm {
-    fn new(message: &str) -> ErrorTerm {
+    fn new(error: &MediatimerError) -> ErrorTerm {
         ErrorTerm {
-           should_exit: false,
-           message: String::from(message)
+            should_exit: false,
+            page: Page::Error,
+            code: error.code(),
+            message: error.message(),
+            context: error.context().lines(),
+            remediation: error.remediation(),
+            config_path: config_path(),
+            diagnostics: None,
         }
     }
-    pub fn run(mut self, terminal: &mut DefaultTerminal) -> Result<(), Box<dyn Error>> {
+
+    pub fn run(mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
         while !self.should_exit {
             terminal.draw(|frame| frame.render_widget(&self, frame.area()))?;
             let e = event::read()?;
@@ -58,52 +520,57 @@ impl ErrorTerm {
         Ok(())
     }
 
-    fn handle_events(&mut self, e: Event) -> Result<(), Box<dyn Error>> {
-        if let Event::Key(key) = e {
-            if key.kind == KeyEventKind::Press {
-               match key.code {
-                   KeyCode::Esc | KeyCode::Char('q') => {
-                       self.should_exit = true;
-                   },
-                   _ => {}
-                }
+    fn handle_events(&mut self, e: Event) -> io::Result<()> {
+        if let Event::Key(key) = e && key.kind == KeyEventKind::Press {
+            match key.code {
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.should_exit = true;
+                },
+                KeyCode::Char('d') => {
+                    self.page = match self.page {
+                        Page::Error => {
+                            self.diagnostics.get_or_insert_with(Diagnostics::collect);
+                            Page::Diagnostics
+                        },
+                        Page::Diagnostics => Page::Error
+                    };
+                },
+                _ => {}
             }
         }
         Ok(())
     }
 
+    fn error_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            String::new(),
+            String::from("Media Timer could not be started."),
+            String::new(),
+            format!("Error {}: {}", self.code, self.message),
+        ];
+        if !self.context.is_empty() {
+            lines.push(String::new());
+            lines.extend(self.context.iter().cloned());
+        }
+        lines.push(String::new());
+        lines.push(String::from("To fix this:"));
+        lines.extend(self.remediation.iter().enumerate().map(|(i, step)| format!("{}. {}", i + 1, step)));
+        lines.push(String::new());
+        lines.push(format!("Config file: {}", self.config_path.display()));
+        lines.push(String::new());
+        lines.push(String::from("Press d for diagnostics, q to quit."));
+        lines
+    }
 }
 
-
-
 impl Widget for &ErrorTerm {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let mut lines = vec![
-                Line::raw(""),
-                Line::raw("Media Timer could not be started."),
-                Line::raw(""),
-                Line::raw("Please run 'mediatimer' in a terminal to setup the program."),
-                Line::raw(""),
-                Line::raw("Or manually edit the Media Timer config file at"),
-                Line::raw("/home/adaptable/.mediatimer_config/vars"),
-        ];
-        if !self.message.is_empty() {
-            lines = vec![
-                Line::raw(""),
-                Line::raw("Media Timer could not be started."),
-                Line::raw(""),
-                Line::raw(&self.message),
-                Line::raw(""),
-                Line::raw("To reset the program run 'mediatimer' in a terminal."),
-                Line::raw(""),
-                Line::raw("Or manually edit the mediatimer config file at"),
-                Line::raw("/home/adaptable/.mediatimer_config/vars"),
-            ];
-        }
-
-        let title = "ERROR";
-
-        Paragraph::new(lines)
+        let (title, lines, alignment) = match (self.page, &self.diagnostics) {
+            (Page::Diagnostics, Some(diagnostics)) => ("DIAGNOSTICS", diagnostics.lines(), Alignment::Left),
+            _ => ("ERROR", self.error_lines(), Alignment::Center)
+        };
+
+        Paragraph::new(lines.into_iter().map(Line::raw).collect::<Vec<Line>>())
             .block(
                 Block::bordered()
                 .black()
@@ -112,8 +579,107 @@ impl Widget for &ErrorTerm {
             )
             .bg(Color::Red)
             .white()
-            .centered()
+            .alignment(alignment)
             .wrap(Wrap { trim: true })
             .render(area, buf)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(MediatimerError::Config(String::from("Missing")).code(), "MT-100");
+        assert_eq!(MediatimerError::Schedule(String::from("Bad window")).to_string(), "MT-500: Bad window");
+        let io = MediatimerError::from(io::Error::other("disk full"));
+        assert_eq!(io.code(), "MT-900");
+        assert!(io.source().is_some());
+        let parsed = MediatimerError::from("x".parse::<u32>().unwrap_err());
+        assert_eq!(parsed.code(), "MT-100");
+    }
+
+    #[test]
+    fn test_context() {
+        let error = MediatimerError::from("abc".parse::<u32>().unwrap_err())
+            .with_setting("MT_SLIDE_DELAY", "abc")
+            .with_path(Path::new("/home/pi/.mediatimer_config/vars"));
+        assert_eq!(error.code(), "MT-100");
+        assert_eq!(error.context().lines(), vec![
+            "Setting: MT_SLIDE_DELAY=abc",
+            "Path: /home/pi/.mediatimer_config/vars",
+        ]);
+        assert_eq!(
+            error.to_string(),
+            "MT-100: Number incorrectly formatted: invalid digit found in string (Setting: MT_SLIDE_DELAY=abc, Path: /home/pi/.mediatimer_config/vars)"
+        );
+        // the detail added closest to the failure is kept
+        let error = error.with_setting("MT_FILE", "/media/pi/A/film.mp4");
+        assert_eq!(error.context().setting, Some((String::from("MT_SLIDE_DELAY"), String::from("abc"))));
+
+        let remediation = error.remediation();
+        assert_eq!(remediation[0], format!("Correct MT_SLIDE_DELAY in the config file at {}.", config_path().display()));
+        assert_eq!(remediation[1], "Run 'mediatimer' in a terminal to set up the program.");
+
+        let mount = MediatimerError::Mount(String::from("Object not found")).with_device("/dev/sda1");
+        assert_eq!(mount.remediation()[0], "Check /dev/sda1 can be read on another computer.");
+        assert_eq!(MediatimerError::Mount(String::from("Object not found")).remediation()[0], "Check the USB storage device is plugged in.");
+    }
+
+    #[test]
+    fn test_error_screen() {
+        let error = MediatimerError::Schedule(String::from("Window end before start")).with_setting("MT_MONDAY", "18:00:00-09:00:00");
+        let lines = ErrorTerm::new(&error).error_lines();
+        assert!(lines.contains(&String::from("Error MT-500: Window end before start")));
+        assert!(lines.contains(&String::from("Setting: MT_MONDAY=18:00:00-09:00:00")));
+        assert!(lines.contains(&format!("1. Correct MT_MONDAY in the config file at {}.", config_path().display())));
+        assert!(lines.contains(&format!("Config file: {}", config_path().display())));
+
+        let diagnostics = Diagnostics {
+            drives: Ok(vec![String::from("sda1 (removable) UUID 5E3A-91C2 mounted at /media/pi/KINGSTON")]),
+            config_path: PathBuf::from("/home/pi/.mediatimer_config/vars"),
+            config: Some(config_lines("# written by mediatimer\nMT_SCHEDULE=true\n\nMT_MONDAY=18:00:00-09:00:00\n")),
+        };
+        assert_eq!(diagnostics.lines(), vec![
+            "Storage devices:",
+            "  sda1 (removable) UUID 5E3A-91C2 mounted at /media/pi/KINGSTON",
+            "",
+            "Config file /home/pi/.mediatimer_config/vars:",
+            "  MT_SCHEDULE=true",
+            "  MT_MONDAY=18:00:00-09:00:00",
+            "",
+            "Press d to go back, q to quit.",
+        ]);
+    }
+
+    #[test]
+    fn test_policy() {
+        assert_eq!(Policy::from_env(None, true), Policy::Interactive);
+        assert_eq!(Policy::from_env(Some("false"), true), Policy::Interactive);
+        assert_eq!(Policy::from_env(Some("true"), true), Policy::Headless);
+        // without a terminal nobody could dismiss the error screen
+        assert_eq!(Policy::from_env(None, false), Policy::Headless);
+    }
+
+    #[test]
+    fn test_error_card() {
+        assert_eq!(wrap("one two three four", 9), vec!["one two", "three", "four"]);
+        assert_eq!(wrap("", 9), Vec::<String>::new());
+
+        let message = card_message(&MediatimerError::Mount(String::from("No storage device matches the saved UUID")));
+        assert_eq!(message, "No storage device matches the saved UUID");
+        let long = card_message(&MediatimerError::Config("word ".repeat(200)));
+        assert_eq!(long.lines().count(), CARD_MAX_LINES);
+        assert!(long.ends_with(" ..."));
+
+        let pgm = qr_pgm("https://example.com/support?code=MT-200").unwrap();
+        let header = String::from_utf8_lossy(&pgm[..pgm.len().min(16)]).to_string();
+        let size: usize = header.split_whitespace().nth(1).unwrap().parse().unwrap();
+        let header_len = format!("P5\n{} {}\n255\n", size, size).len();
+        assert_eq!(pgm.len(), header_len + size * size);
+        // the quiet zone is light and the finder pattern in the corner is dark
+        assert_eq!(pgm[header_len], 255);
+        assert_eq!(pgm[header_len + 4 * size + 4], 0);
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        Mutex
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::TimeDelta;
+
+use crate::{
+    logi,
+    loge
+};
+use log::{
+    info,
+    error
+};
+
+use crate::{
+    ProcType,
+    Task,
+    error::MediatimerError,
+    notify,
+    playback::Playback,
+    scheduler::{
+        InterruptTime,
+        Timetable
+    },
+    task_runner::run_task
+};
+
+/// How often an interrupt without a duration is checked to see if its player has exited
+const EXIT_POLL: Duration = Duration::from_millis(500);
+
+/// An interrupt as read from the `MT_INTERRUPT_<NAME>_<FIELD>` variables
+#[derive(Debug, Clone)]
+pub struct InterruptConfig {
+    pub name: String,
+    pub at: Option<InterruptTime>,
+    pub proc_type: ProcType,
+    pub file: PathBuf,
+    pub web_url: String,
+    pub duration: Option<TimeDelta>,
+    pub priority: u32,
+}
+
+impl InterruptConfig {
+    pub fn new(name: &str) -> InterruptConfig {
+        InterruptConfig {
+            name: String::from(name),
+            at: None,
+            proc_type: ProcType::Video,
+            file: PathBuf::new(),
+            web_url: String::new(),
+            duration: None,
+            priority: 1,
+        }
+    }
+}
+
+/// A task that pre-empts the scheduled programme and then hands back to it
+#[derive(Debug)]
+pub struct Interrupt {
+    pub name: String,
+    pub priority: u32,
+    pub task: Arc<Mutex<Task>>,
+    /// Without a duration the interrupt ends when its player exits
+    pub run_until_exit: bool,
+}
+
+/// The interrupt currently playing and the pid of its player
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Active {
+    index: usize,
+    pid: u32,
+}
+
+/// Starts and ends interrupts. At most one interrupt plays at a time; an interrupt only
+/// pre-empts another with a lower priority. When an interrupt ends the scheduled task is
+/// resumed at the position it would have reached, or the background is shown if no window
+/// is active.
+#[derive(Clone)]
+pub struct Interrupts {
+    interrupts: Arc<Vec<Interrupt>>,
+    active: Arc<Mutex<Option<Active>>>,
+    timetable: Arc<Timetable>,
+    task: Arc<Mutex<Task>>,
+    playback: Arc<Playback>,
+}
+
+impl Interrupts {
+    pub fn new(interrupts: Vec<Interrupt>, timetable: Arc<Timetable>, task: Arc<Mutex<Task>>, playback: Arc<Playback>) -> Interrupts {
+        Interrupts {
+            interrupts: Arc::new(interrupts),
+            active: Arc::new(Mutex::new(None)),
+            timetable,
+            task,
+            playback,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.lock().unwrap().is_some()
+    }
+
+    pub fn begin(&self, index: usize) -> Result<(), MediatimerError> {
+        let interrupt = &self.interrupts[index];
+        let mut active = self.active.lock().unwrap();
+        if let Some(current) = *active {
+            let current = &self.interrupts[current.index];
+            if current.priority >= interrupt.priority {
+                logi!("Interrupt {} skipped, {} has priority {} and is still playing", interrupt.name, current.name, current.priority);
+                return Ok(());
+            }
+            logi!("Interrupt {} pre-empts {}", interrupt.name, current.name);
+        }
+
+        logi!("Starting interrupt {}", interrupt.name);
+        // whatever was playing is stopped once the interrupt has started, to allow overlap
+        let started = self.playback.play(&interrupt.task.lock().unwrap(), "0")
+            .map_err(|e| MediatimerError::Spawn(format!("Failed to start interrupt {}: {}", interrupt.name, e)))?;
+        let pid = started.pid;
+        logi!(MT_EVENT = "interrupt", MT_TASK = interrupt.name.as_str(), MT_PROCTYPE = interrupt.task.lock().unwrap().proc_type.as_str(), MT_PID = pid;
+            "Interrupt {} started with pid {}", interrupt.name, pid);
+        notify::playing_interrupt(&interrupt.name, started.scope.as_deref());
+        *active = Some(Active { index, pid });
+        drop(active);
+
+        if interrupt.run_until_exit {
+            let interrupts = self.clone();
+            thread::spawn(move || interrupts.watch(index, pid));
+        }
+        Ok(())
+    }
+
+    pub fn end(&self, index: usize) -> Result<(), MediatimerError> {
+        let mut active = self.active.lock().unwrap();
+        // the interrupt may already have finished or been pre-empted by another
+        if active.is_none_or(|a| a.index != index) {
+            return Ok(());
+        }
+        *active = None;
+        logi!(MT_EVENT = "interrupt_end", MT_TASK = self.interrupts[index].name.as_str(); "Ending interrupt {}", self.interrupts[index].name);
+
+        // the scheduled task or the background replaces the interrupt once it has started
+        match self.timetable.active_window(self.timetable.now()) {
+            Some(window_start) => {
+                logi!("Resuming the scheduled task from the window that started at {}", window_start);
+                run_task(&self.playback, &self.task, Some(window_start))
+            },
+            None => {
+                logi!("No window active after interrupt, showing the background");
+                self.playback.stop()?;
+                notify::status("Showing the background");
+                Ok(())
+            }
+        }
+    }
+
+    /// Ends the interrupt when its player exits
+    fn watch(&self, index: usize, pid: u32) {
+        loop {
+            thread::sleep(EXIT_POLL);
+            if *self.active.lock().unwrap() != Some(Active { index, pid }) {
+                return;
+            }
+            if self.playback.has_exited(pid) {
+                logi!("Interrupt {} finished playing", self.interrupts[index].name);
+                if let Err(e) = self.end(index) {
+                    loge!("Failed to end interrupt: {}", e);
+                }
+                return;
+            }
+        }
+    }
+}
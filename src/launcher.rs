@@ -0,0 +1,83 @@
+use std::{
+    io,
+    os::unix::process::CommandExt,
+    process::{
+        Child,
+        Command
+    },
+};
+
+use crate::logd;
+use log::debug;
+
+/// Starts the player for a task from a fully built command. Tasks are always launched
+/// through this so tests can record the command lines and stand in for the players.
+/// Every task is started in its own process group, so it can be stopped along with
+/// anything it starts itself.
+pub trait Launcher: Send + Sync {
+    fn spawn(&self, command: &mut Command) -> io::Result<Child>;
+}
+
+/// Runs the command as given
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemLauncher;
+
+impl Launcher for SystemLauncher {
+    fn spawn(&self, command: &mut Command) -> io::Result<Child> {
+        logd!("Launching {:?}", command);
+        command.process_group(0).spawn()
+    }
+}
+
+/// A launcher for tests that records each command line and starts a shell in place of the
+/// player, which keeps running, exits or crashes as asked.
+#[cfg(test)]
+pub mod mock {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Outcome {
+        /// Keeps running until killed
+        Run,
+        /// Exits by itself with the code
+        Exit(i32),
+        /// Killed by a segmentation fault
+        Crash,
+    }
+
+    pub struct MockLauncher {
+        pub outcome: Outcome,
+        pub launched: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl MockLauncher {
+        pub fn new(outcome: Outcome) -> MockLauncher {
+            MockLauncher {
+                outcome,
+                launched: Mutex::new(Vec::new()),
+            }
+        }
+
+        /// The command lines launched so far, program first
+        pub fn launched(&self) -> Vec<Vec<String>> {
+            self.launched.lock().unwrap().clone()
+        }
+    }
+
+    impl Launcher for MockLauncher {
+        fn spawn(&self, command: &mut Command) -> io::Result<Child> {
+            let mut line = vec![command.get_program().to_string_lossy().to_string()];
+            line.extend(command.get_args().map(|a| a.to_string_lossy().to_string()));
+            self.launched.lock().unwrap().push(line);
+
+            let script = match self.outcome {
+                Outcome::Run => String::from("sleep 30"),
+                Outcome::Exit(code) => format!("exit {}", code),
+                Outcome::Crash => String::from("kill -SEGV $$")
+            };
+            Command::new("sh").arg("-c").arg(script).process_group(0).spawn()
+        }
+    }
+}
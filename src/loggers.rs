@@ -1,13 +1,268 @@
-use log::{info, warn, error, LevelFilter};
-use std::error::Error;
+use log::{
+    LevelFilter,
+    Log,
+    Metadata,
+    Record,
+    kv::{
+        self,
+        Key,
+        Value,
+        VisitSource
+    }
+};
+use std::{
+    env,
+    fs::{
+        self,
+        File,
+        OpenOptions
+    },
+    io::{
+        self,
+        Write
+    },
+    path::{
+        Path,
+        PathBuf
+    },
+    str::FromStr,
+    sync::Mutex,
+    time::{
+        Duration,
+        SystemTime
+    },
+};
 use systemd_journal_logger::JournalLog;
 
-pub fn setup_logger() -> Result<(), Box<dyn Error>> {
-    JournalLog::new()?.install()?;
-    log::set_max_level(LevelFilter::Info);
+use crate::{
+    error::MediatimerError,
+    timezone
+};
+
+/// The name of the current log file. Rotated files have a number added, `.1` being the newest.
+const LOG_NAME: &str = "mediatimer.log";
+
+/// The size the log file may grow to before it is rotated
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// The number of rotated log files kept alongside the current one
+const MAX_LOG_FILES: usize = 5;
+
+/// Rotated log files older than this are removed
+const MAX_LOG_AGE: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// The level logged until `MT_LOG_LEVEL` is read
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+
+/// A file on the root of a USB drive asking for the logs to be copied onto it
+const COLLECT_LOGS_MARKER: &str = "collect-logs";
+
+/// The folder on the USB drive the logs are copied into
+const EXPORT_DIR: &str = "mediatimer-logs";
+
+pub fn log_dir() -> PathBuf {
+    let username = whoami::username();
+    ["/home/", &username, ".mediatimer_config/logs"].iter().collect()
+}
+
+fn rotated_path(dir: &Path, number: usize) -> PathBuf {
+    dir.join(format!("{}.{}", LOG_NAME, number))
+}
+
+/// Removes rotated log files last written before `now - MAX_LOG_AGE`
+fn remove_expired(dir: &Path, now: SystemTime) -> io::Result<()> {
+    let Some(cutoff) = now.checked_sub(MAX_LOG_AGE) else {
+        return Ok(());
+    };
+    for entry in fs::read_dir(dir)?.flatten() {
+        let rotated = entry.file_name().to_string_lossy().starts_with(&format!("{}.", LOG_NAME));
+        let expired = entry.metadata().and_then(|m| m.modified()).is_ok_and(|modified| modified < cutoff);
+        if rotated && expired {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+struct LogFile {
+    file: File,
+    size: u64,
+}
+
+/// A log file that is rotated once it reaches `max_size`, keeping `MAX_LOG_FILES` old files
+struct RotatingFile {
+    dir: PathBuf,
+    max_size: u64,
+    current: Mutex<LogFile>,
+}
+
+impl RotatingFile {
+    fn open(dir: &Path, max_size: u64) -> io::Result<RotatingFile> {
+        fs::create_dir_all(dir)?;
+        remove_expired(dir, SystemTime::now())?;
+        Ok(RotatingFile {
+            dir: dir.to_path_buf(),
+            max_size,
+            current: Mutex::new(RotatingFile::open_current(dir)?),
+        })
+    }
+
+    fn open_current(dir: &Path) -> io::Result<LogFile> {
+        let file = OpenOptions::new().create(true).append(true).open(dir.join(LOG_NAME))?;
+        let size = file.metadata()?.len();
+        Ok(LogFile { file, size })
+    }
+
+    fn write_line(&self, line: &str) -> io::Result<()> {
+        let mut current = self.current.lock().unwrap();
+        if current.size > 0 && current.size + line.len() as u64 > self.max_size {
+            *current = self.rotate()?;
+        }
+        current.file.write_all(line.as_bytes())?;
+        current.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Moves each log file up a number, dropping the oldest, and starts a new current file
+    fn rotate(&self) -> io::Result<LogFile> {
+        for number in (1..MAX_LOG_FILES).rev() {
+            let from = rotated_path(&self.dir, number);
+            if from.exists() {
+                fs::rename(from, rotated_path(&self.dir, number + 1))?;
+            }
+        }
+        fs::rename(self.dir.join(LOG_NAME), rotated_path(&self.dir, 1))?;
+        remove_expired(&self.dir, SystemTime::now())?;
+        RotatingFile::open_current(&self.dir)
+    }
+}
+
+/// Collects the structured fields of a record as ` KEY=value` pairs
+struct Fields(String);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push_str(&format!(" {}={}", key, value));
+        Ok(())
+    }
+}
+
+/// A line of the log file. Structured fields follow the message, as the journal keeps them
+/// apart from it.
+fn format_line(record: &Record, time: &str) -> String {
+    let mut fields = Fields(String::new());
+    let _ = record.key_values().visit(&mut fields);
+    format!("{} {:<5} {}{}\n", time, record.level(), record.args(), fields.0)
+}
+
+/// Reads an `MT_LOG_LEVEL` value such as "debug" or "warn"
+fn parse_level(value: &str) -> Result<LevelFilter, MediatimerError> {
+    LevelFilter::from_str(value.trim())
+        .map_err(|_| MediatimerError::Config(format!("Log level {} not recognised, please use off, error, warn, info, debug or trace", value)))
+}
+
+/// Sets the most detailed level that is logged, from an `MT_LOG_LEVEL` value
+pub fn set_level(value: &str) -> Result<(), MediatimerError> {
+    let level = parse_level(value)?;
+    log::set_max_level(level);
     Ok(())
 }
 
+/// Sends every record to the journal and to the log file, whichever of them could be opened
+struct Loggers {
+    journal: Option<JournalLog>,
+    file: Option<RotatingFile>,
+}
+
+impl Log for Loggers {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if let Some(journal) = &self.journal {
+            journal.log(record);
+        }
+        if let Some(file) = &self.file {
+            let line = format_line(record, &timezone::now_for_logs().format("%Y-%m-%d %H:%M:%S %z").to_string());
+            // there is nowhere left to report a failed write
+            let _ = file.write_line(&line);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(journal) = &self.journal {
+            journal.flush();
+        }
+        if let Some(file) = &self.file {
+            let _ = file.current.lock().unwrap().file.flush();
+        }
+    }
+}
+
+/// Logs to journald and to a rotating file under `~/.mediatimer_config/logs`, which can be
+/// read without access to the journal. If either cannot be opened the other is still used
+/// and the error is returned. The level is taken from `MT_LOG_LEVEL` in the service
+/// environment, and can be changed again once the config file is read.
+pub fn setup_logger() -> Result<(), MediatimerError> {
+    let mut errors: Vec<MediatimerError> = Vec::new();
+    let journal = JournalLog::new().map_err(|e| errors.push(e.into())).ok();
+    let file = RotatingFile::open(&log_dir(), MAX_LOG_SIZE).map_err(|e| errors.push(e.into())).ok();
+    log::set_boxed_logger(Box::new(Loggers { journal, file }))
+        .map_err(io::Error::other)?;
+    log::set_max_level(DEFAULT_LEVEL);
+    if let Ok(value) = env::var("MT_LOG_LEVEL")
+        && let Err(e) = set_level(&value) {
+        errors.push(e);
+    }
+    match errors.into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(())
+    }
+}
+
+/// Copies the log files and status file onto `drive` if a technician has put a
+/// `collect-logs` file on it, then removes the file so the logs are only copied once. Returns
+/// the folder the logs were copied to.
+pub fn export_logs(drive: &Path) -> Result<Option<PathBuf>, MediatimerError> {
+    log::logger().flush();
+    let hostname = whoami::fallible::hostname().unwrap_or_else(|_| String::from("mediatimer"));
+    let name = format!("{}-{}", hostname, timezone::now_for_logs().format("%Y%m%d-%H%M%S"));
+    export_from(&log_dir(), drive, &name)
+}
+
+fn export_from(log_dir: &Path, drive: &Path, name: &str) -> Result<Option<PathBuf>, MediatimerError> {
+    let marker = drive.join(COLLECT_LOGS_MARKER);
+    if !marker.exists() {
+        return Ok(None);
+    }
+    let export_dir = drive.join(EXPORT_DIR).join(name);
+    fs::create_dir_all(&export_dir)
+        .map_err(|e| MediatimerError::Io(e).with_path(&export_dir))?;
+
+    let mut files = fs::read_dir(log_dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.file_name().is_some_and(|n| n.to_string_lossy().starts_with(LOG_NAME)))
+        .collect::<Vec<PathBuf>>();
+    if let Some(status) = log_dir.parent().map(|config| config.join("status")).filter(|s| s.exists()) {
+        files.push(status);
+    }
+    for file in files.iter() {
+        if let Some(file_name) = file.file_name() {
+            fs::copy(file, export_dir.join(file_name))
+                .map_err(|e| MediatimerError::Io(e).with_path(file))?;
+        }
+    }
+    // start up is retried, and would otherwise fill the drive with copies
+    fs::remove_file(&marker)
+        .map_err(|e| MediatimerError::Io(e).with_path(&marker))?;
+    Ok(Some(export_dir))
+}
+
 #[macro_export] 
 macro_rules! logi {
     ($($t:tt)*) => {{
@@ -24,10 +279,127 @@ macro_rules! logw {
 
 #[macro_export] 
 macro_rules! loge {
+    // structured fields go to the journal only
+    ($($k:ident = $v:expr),+ ; $($t:tt)*) => {{
+       error!($($k = $v),+ ; $($t)*);
+       eprintln!($($t)*);
+    }};
     ($($t:tt)*) => {{
        error!($($t)*); 
        eprintln!($($t)*);
     }};
 }
 
+#[macro_export] 
+macro_rules! logd {
+    ($($t:tt)*) => {{
+       debug!($($t)*); 
+    }};
+}
+
+/// Keeps what the tests log, as lines of the log file, so they can check a record was written
+#[cfg(test)]
+pub mod capture {
+    use super::*;
+    use std::sync::Once;
 
+    static LINES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    struct Capture;
+
+    impl Log for Capture {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= log::max_level()
+        }
+
+        fn log(&self, record: &Record) {
+            LINES.lock().unwrap().push(format_line(record, "-"));
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Starts capturing records. The logger can only be set once, so it is shared by every test.
+    pub fn start() {
+        static START: Once = Once::new();
+        START.call_once(|| {
+            log::set_logger(&Capture).unwrap();
+            log::set_max_level(LevelFilter::Debug);
+        });
+    }
+
+    /// The lines logged so far, by every test
+    pub fn lines() -> Vec<String> {
+        LINES.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = RotatingFile::open(dir.path(), 100).unwrap();
+        let line = format!("{}\n", "x".repeat(39));
+        for _ in 0..(2 * (MAX_LOG_FILES + 2)) {
+            log.write_line(&line).unwrap();
+        }
+        // two lines fit in each file and only MAX_LOG_FILES rotated files are kept
+        assert_eq!(fs::read_to_string(dir.path().join(LOG_NAME)).unwrap(), line.repeat(2));
+        for number in 1..=MAX_LOG_FILES {
+            assert_eq!(fs::read_to_string(rotated_path(dir.path(), number)).unwrap(), line.repeat(2));
+        }
+        assert!(!rotated_path(dir.path(), MAX_LOG_FILES + 1).exists());
+
+        // rotated files past the age limit are removed, the current file is kept
+        remove_expired(dir.path(), SystemTime::now() + MAX_LOG_AGE + Duration::from_secs(60)).unwrap();
+        assert!(dir.path().join(LOG_NAME).exists());
+        assert!(!rotated_path(dir.path(), 1).exists());
+    }
+
+    #[test]
+    fn test_format_line() {
+        let fields = [("MT_EVENT", "start"), ("MT_PID", "4312")];
+        let record = Record::builder()
+            .level(log::Level::Info)
+            .args(format_args!("Task started"))
+            .key_values(&fields)
+            .build();
+        assert_eq!(format_line(&record, "2026-10-18 09:00:00 +0100"), "2026-10-18 09:00:00 +0100 INFO  Task started MT_EVENT=start MT_PID=4312\n");
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("debug").unwrap(), LevelFilter::Debug);
+        assert_eq!(parse_level("WARN").unwrap(), LevelFilter::Warn);
+        assert_eq!(parse_level(" off ").unwrap(), LevelFilter::Off);
+        assert_eq!(parse_level("loud").unwrap_err().code(), "MT-100");
+    }
+
+    #[test]
+    fn test_export_logs() {
+        let config = tempfile::tempdir().unwrap();
+        let logs = config.path().join("logs");
+        let log = RotatingFile::open(&logs, MAX_LOG_SIZE).unwrap();
+        log.write_line("2026-10-18 09:00:00 +0100 INFO  Initialising\n").unwrap();
+        fs::write(config.path().join("status"), "MT_ERROR=MT-200\n").unwrap();
+        let drive = tempfile::tempdir().unwrap();
+
+        // nothing is copied without the marker
+        assert_eq!(export_from(&logs, drive.path(), "unit-1").unwrap(), None);
+        assert!(!drive.path().join(EXPORT_DIR).exists());
+
+        fs::write(drive.path().join(COLLECT_LOGS_MARKER), "").unwrap();
+        let exported = export_from(&logs, drive.path(), "unit-1").unwrap().unwrap();
+        assert_eq!(exported, drive.path().join(EXPORT_DIR).join("unit-1"));
+        assert!(fs::read_to_string(exported.join(LOG_NAME)).unwrap().contains("Initialising"));
+        assert_eq!(fs::read_to_string(exported.join("status")).unwrap(), "MT_ERROR=MT-200\n");
+
+        // the marker is removed, so the logs are not copied again when start up is retried
+        assert!(!drive.path().join(COLLECT_LOGS_MARKER).exists());
+        assert_eq!(export_from(&logs, drive.path(), "unit-2").unwrap(), None);
+        assert!(!drive.path().join(EXPORT_DIR).join("unit-2").exists());
+    }
+}
@@ -1,10 +1,10 @@
 use std::{
+    convert::Infallible,
     sync::{Arc, Mutex},
     thread,
     time::Duration,
     path::{Path, PathBuf},
     env,
-    error::Error,
     process,
     process::{
         Command,
@@ -19,15 +19,10 @@ use std::{
 };
 use strum::Display;
 
-use clokwerk::{
-    Scheduler,
-    Interval,
-    Job
-};
-
 use chrono::{
     Local,
-    TimeZone
+    NaiveDate,
+    TimeDelta
 };
 
 use regex::Regex;
@@ -46,11 +41,62 @@ use crate::mount::{
 mod background;
 
 mod error;
-use crate::error::error_with_message as display_error_with_message;
+use crate::error::{
+    HEADLESS_RETRY,
+    MediatimerError,
+    Policy,
+    display_error
+};
 
 mod task_runner;
 use crate::task_runner::run_task;
 
+mod launcher;
+use crate::launcher::SystemLauncher;
+
+mod scheduler;
+use crate::scheduler::{
+    Action,
+    InterruptSchedule,
+    InterruptTime,
+    Season,
+    Timetable
+};
+
+mod interrupt;
+use crate::interrupt::{
+    Interrupt,
+    InterruptConfig,
+    Interrupts
+};
+
+mod status;
+
+mod notify;
+
+mod shutdown;
+mod reaper;
+mod scope;
+mod playback;
+use crate::playback::Playback;
+
+mod clock;
+
+mod time_sync;
+
+mod timezone;
+
+mod solar;
+use crate::solar::Location;
+
+mod cron;
+use crate::cron::CronSchedule;
+
+mod preview;
+
+mod proof;
+use crate::proof::Play;
+
 #[derive(Debug,Clone, Copy, PartialEq)]
 pub enum ProcType {
     Video,
@@ -62,6 +108,20 @@ pub enum ProcType {
     Executable,
 }
 
+impl ProcType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ProcType::Video => "video",
+            ProcType::Audio => "audio",
+            ProcType::Image => "image",
+            ProcType::Slideshow => "slideshow",
+            ProcType::Web => "web",
+            ProcType::Browser => "browser",
+            ProcType::Executable => "executable"
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 enum Autoloop {
     Yes,
@@ -74,6 +134,14 @@ pub enum AdvancedSchedule {
     No
 }
 
+/// A dry run, such as `--preview`, only reads the config. Drives are not mounted and logs
+/// are not copied to them.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum DryRun {
+    Yes,
+    No
+}
+
 type Schedule = Vec<(String, String)>;
 
 #[derive(Display, Debug, Clone)]
@@ -88,6 +156,18 @@ pub enum Weekday {
 }
 
 impl Weekday {
+    fn schedule(&self) -> &Schedule {
+        match self {
+            Weekday::Monday(s) => s,
+            Weekday::Tuesday(s) => s,
+            Weekday::Wednesday(s) => s,
+            Weekday::Thursday(s) => s,
+            Weekday::Friday(s) => s,
+            Weekday::Saturday(s) => s,
+            Weekday::Sunday(s) => s
+        }
+    }
+
     fn as_str(&self) -> &'static str {
         match self {
             Weekday::Monday(_) => "Monday",
@@ -111,7 +191,7 @@ enum Model {
 /// Commonly this is used for playing media files at certain times.
 /// The Task struct is the main set of instructions that are written out into an env file to be 
 /// interpreted in future by the init program.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Task {
     model: Model,
     proc_type: ProcType,
@@ -132,38 +212,68 @@ impl Task {
             web_url
         }
     }
+
+    /// The file or URL the task plays
+    fn source(&self) -> String {
+        match self.proc_type {
+            ProcType::Web => self.web_url.clone(),
+            _ => self.file.to_string_lossy().to_string()
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct RunningTask {
     child: process::Child,
-    background: bool,
+    /// Recorded to the proof of play log when the task ends
+    play: Option<Play>,
+    /// The systemd scope the task runs in, when `MT_SCOPE` is set
+    scope: Option<String>,
 }
 
 impl RunningTask {
-    fn new(child: Child, background: bool) -> RunningTask {
+    fn new(child: Child) -> RunningTask {
         logi!("Initialising running task");
         RunningTask {
             child,
-            background,
+            play: None,
+            scope: None,
         }
     }
+
+    fn with_play(mut self, play: Play) -> RunningTask {
+        play.watch(self.child.id());
+        self.play = Some(play);
+        self
+    }
+
+    fn with_scope(mut self, scope: String) -> RunningTask {
+        self.scope = Some(scope);
+        self
+    }
 }
 
-fn timing_format_correct(string_of_times: &str) -> Result<bool, Box<dyn Error>> {
+/// A window bound relative to sunrise or sunset, e.g. "sunset-00:30" or "sunrise+01:00"
+const SOLAR_PATTERN: &str = r"(?:sunrise|sunset)(?:[+-][0-2][0-9]:[0-5][0-9](?::[0-5][0-9])?)?";
+
+/// An optional repeat rule after a window, such as " every 20m" or " every 20m for 5m"
+const REPEAT_PATTERN: &str = r"(?:\s+every\s+[0-9]+[smh]?(?:\s+for\s+[0-9]+[smh]?)?)?";
+
+fn timing_format_correct(string_of_times: &str) -> Result<bool, MediatimerError> {
     logi!("Checking timing format");
-    let re = Regex::new(r"^(?<start>[0-2][0-9]):[0-5][0-9]:[0-5][0-9]-(?<end>[0-2][0-9]):[0-5][0-9]:[0-5][0-9]$")?;
+    let re = Regex::new(&format!(
+        r"^(?:(?<start>[0-2][0-9]):[0-5][0-9]:[0-5][0-9]|{SOLAR_PATTERN})-(?:(?<end>[0-2][0-9]):[0-5][0-9]:[0-5][0-9]|{SOLAR_PATTERN}){REPEAT_PATTERN}$"
+    ))?;
     if re.is_match(string_of_times) { 
         if let Some(captured) = re.captures(string_of_times) {
-            let (_, [start, end]) = captured.extract();
-            let hour_1 = start.parse::<u32>()?;
-            let hour_2 = end.parse::<u32>()?;
-
             // This checks if the hour is less than 24
             // The minutes and seconds are already checked by the regex
-            if hour_1 < 24 && hour_2 < 24 {
-                return Ok(true);
+            for hour in [captured.name("start"), captured.name("end")].into_iter().flatten() {
+                if hour.as_str().parse::<u32>()? >= 24 {
+                    return Ok(false);
+                }
             }
+            return Ok(true);
         } else {
             logw!("Timing format could not be captured in regex")
         }
@@ -171,15 +281,32 @@ fn timing_format_correct(string_of_times: &str) -> Result<bool, Box<dyn Error>>
     Ok(false)
 }
 
+/// Splits a window such as "08:00:00-12:00:00" or "sunset-00:30-23:00:00" into its start
+/// and end. Solar bounds may contain a '-' themselves, so the split is made where both
+/// halves are valid bounds. Any repeat rule is kept on the end for the scheduler to read.
+fn split_window(window: &str) -> Result<(String, String), MediatimerError> {
+    let bound = format!(r"{SOLAR_PATTERN}|[0-2]?[0-9]:[0-5][0-9](?::[0-5][0-9])?");
+    let re = Regex::new(&format!(r"^(?<start>{bound})-(?<end>(?:{bound}){REPEAT_PATTERN})$"))?;
+    if let Some(captured) = re.captures(window) {
+        let (_, [start, end]) = captured.extract();
+        return Ok((start.to_string(), end.to_string()));
+    }
+    let start_end = window
+        .split("-")
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>();
+    Ok((start_end[0].clone(), start_end[1].clone()))
+}
+
 
-fn url_format_correct(url: &str) -> Result<bool, Box<dyn Error>> {
+fn url_format_correct(url: &str) -> Result<bool, MediatimerError> {
     logi!("Checking URL format");
     let re = Regex::new(r"^(https?://)?([\da-z\.-]+)\.([a-z\.]{2,6})([\/\w \.-]*)*\/?$")?;
     Ok(re.is_match(url))
 }
 
 
-fn to_weekday(value: String, day: Weekday, schedule: AdvancedSchedule) -> Result<Weekday, Box<dyn Error>> {
+fn to_weekday(value: String, day: Weekday, schedule: AdvancedSchedule) -> Result<Weekday, MediatimerError> {
 
 
     let mut day_schedule = Vec::new();
@@ -190,18 +317,12 @@ fn to_weekday(value: String, day: Weekday, schedule: AdvancedSchedule) -> Result
         for start_and_end in string_vec.iter() {
             let timing_format_correct = timing_format_correct(start_and_end)?;
             if schedule == AdvancedSchedule::Yes && !timing_format_correct {
-                display_error_with_message("Schedule incorrectly formatted!");
+                return Err(MediatimerError::Schedule(format!("Schedule incorrectly formatted! {} is not a valid window.", start_and_end)));
             }
         }
 
         for time in string_vec.iter() {
-            let start_end = time.as_str()
-                .split("-")
-                .map(|x| x.to_string())
-                .collect::<Vec<String>>();
-            let start = start_end[0].clone();
-            let end = start_end[1].clone();
-            day_schedule.push((start, end));
+            day_schedule.push(split_window(time)?);
         }
     }
 
@@ -217,60 +338,134 @@ fn to_weekday(value: String, day: Weekday, schedule: AdvancedSchedule) -> Result
 }
 
 
+fn to_proc_type(value: &str) -> ProcType {
+    match value {
+        "video" => ProcType::Video,
+        "audio" => ProcType::Audio,
+        "image" => ProcType::Image,
+        "slideshow" => ProcType::Slideshow,
+        "web" => ProcType::Web,
+        "browser" => ProcType::Browser,
+        "executable" => ProcType::Executable,
+        &_ => ProcType::Video
+    }
+}
 
-fn stop_task(task_list: Arc<Mutex<Vec<RunningTask>>>) -> Result<(), Box<dyn Error>> {
-
-    if !task_list.lock().unwrap().is_empty() {
-
-        let mut task = task_list.lock().unwrap().remove(0);
-
-        logi!("Attempting to Kill Task: {:?}", task.child);
-
-        task.child.kill()?;
+/// Reads one `MT_INTERRUPT_<NAME>_<FIELD>` variable into the named interrupt. The fields are
+/// `AT`, either "HH:MM:SS" to interrupt every day or "YYYY-MM-DD HH:MM:SS" to interrupt
+/// once, `PROCTYPE`, `FILE`, `URL`, `DURATION` in seconds and `PRIORITY`, where a higher
+/// priority interrupt pre-empts a lower one.
+fn to_interrupt(interrupts: &mut Vec<InterruptConfig>, key: &str, value: String) -> Result<(), MediatimerError> {
+    let Some((name, field)) = key.trim_start_matches("MT_INTERRUPT_").rsplit_once('_') else {
+        logw!("Interrupt variable {} not recognised", key);
+        return Ok(());
+    };
+    let index = match interrupts.iter().position(|i| i.name == name) {
+        Some(index) => index,
+        None => {
+            interrupts.push(InterruptConfig::new(name));
+            interrupts.len() - 1
+        }
+    };
+    let interrupt = &mut interrupts[index];
+
+    match field {
+        "AT" => match InterruptTime::parse(&value) {
+            Ok(at) => interrupt.at = Some(at),
+            Err(e) => {
+                loge!("Interrupt {} time {} incorrectly formatted: {}", name, value, e);
+                return Err(MediatimerError::Schedule(format!("Interrupt {} time incorrectly formatted!", name)));
+            }
+        },
+        "PROCTYPE" => interrupt.proc_type = to_proc_type(&value),
+        "FILE" => interrupt.file = PathBuf::from(value),
+        "URL" => interrupt.web_url = value,
+        "DURATION" => interrupt.duration = Some(TimeDelta::seconds(value.parse::<i64>()?)),
+        "PRIORITY" => interrupt.priority = value.parse::<u32>()?,
+        _ => logw!("Interrupt variable {} not recognised", key)
+    }
+    Ok(())
+}
 
+/// Reads one `MT_SEASON_<NAME>_<FIELD>` variable into the named season. The fields are
+/// `FROM` and `UNTIL`, dates formatted as YYYY-MM-DD, and the days `MONDAY` to `SUNDAY`,
+/// formatted as for the `MT_<DAY>` variables.
+fn to_season(seasons: &mut Vec<Season>, key: &str, value: String, schedule: AdvancedSchedule) -> Result<(), MediatimerError> {
+    let Some((name, field)) = key.trim_start_matches("MT_SEASON_").rsplit_once('_') else {
+        logw!("Season variable {} not recognised", key);
+        return Ok(());
+    };
+    let index = match seasons.iter().position(|s| s.name == name) {
+        Some(index) => index,
+        None => {
+            seasons.push(Season::new(name));
+            seasons.len() - 1
+        }
+    };
+    let season = &mut seasons[index];
 
+    let to_date = |value: &str| match NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d") {
+        Ok(date) => Ok(Some(date)),
+        Err(e) => {
+            loge!("Season {} date {} incorrectly formatted: {}", name, value, e);
+            Err(MediatimerError::Schedule(format!("Season {} dates incorrectly formatted! Please use YYYY-MM-DD.", name)))
+        }
+    };
 
-        if !task.background {
-            // clears up any sub processes: particularly needed for "executable" 
-            // proctypes as anything spawned from a sub shell will likely have a different PID
-            logi!("Attempting to kill any subprocesses");
-            let id = task.child.id();
-            let neg_id = format!("-{}", id);
-            let _kill_child = Command::new("kill")
-                .arg("-TERM")
-                .arg("--")
-                .arg(neg_id)
-                .output()?;
+    match field {
+        "FROM" => season.valid_from = to_date(&value)?,
+        "UNTIL" => season.valid_until = to_date(&value)?,
+        "MONDAY" => season.days.push(to_weekday(value, Weekday::Monday(Vec::new()), schedule)?),
+        "TUESDAY" => season.days.push(to_weekday(value, Weekday::Tuesday(Vec::new()), schedule)?),
+        "WEDNESDAY" => season.days.push(to_weekday(value, Weekday::Wednesday(Vec::new()), schedule)?),
+        "THURSDAY" => season.days.push(to_weekday(value, Weekday::Thursday(Vec::new()), schedule)?),
+        "FRIDAY" => season.days.push(to_weekday(value, Weekday::Friday(Vec::new()), schedule)?),
+        "SATURDAY" => season.days.push(to_weekday(value, Weekday::Saturday(Vec::new()), schedule)?),
+        "SUNDAY" => season.days.push(to_weekday(value, Weekday::Sunday(Vec::new()), schedule)?),
+        _ => logw!("Season variable {} not recognised", key)
+    }
+    Ok(())
+}
 
-            logi!("Killed task was not background; attempting to start background");
-            // run background
-            background::run(Arc::clone(&task_list))?;
-        } else {
-            logi!("Killed task was background");
+/// Parses a ';' separated list of cron expressions from `MT_CRON_START` or `MT_CRON_STOP`.
+/// These can be used instead of, or as well as, the `MT_<DAY>` windows.
+fn to_cron(value: &str) -> Result<Vec<CronSchedule>, MediatimerError> {
+    match cron::parse_list(value) {
+        Ok(schedules) => Ok(schedules),
+        Err(e) => {
+            loge!("{}", e);
+            Err(e)
         }
+    }
+}
 
-        // wait for a second before stopping the task, to allow overlap
-        let one_sec = Duration::from_millis(1000);
-        thread::sleep(one_sec);
+/// The config file written by the `mediatimer` program
+fn config_path() -> PathBuf {
+    let username = whoami::username();
+    ["/home/", &username, ".mediatimer_config/vars"].iter().collect()
+}
 
-        task.child.kill()?;
+/// Falls back to the background when a task could not be started or stopped, so the screen
+/// is never left empty. Nothing is done if something is still playing.
+fn show_background(playback: &Playback) {
+    if let Err(e) = playback.show_background() {
+        loge!("Failed to run background: {}", e);
     }
-    Ok(())
 }
 
 struct App {
-    task_list: Arc<Mutex<Vec<RunningTask>>>,
+    playback: Arc<Playback>,
 }
 
 impl Default for App {
     fn default() -> Self {
         App {
-            task_list: Arc::new(Mutex::new(Vec::new()))
+            playback: Arc::new(Playback::new(Arc::new(SystemLauncher)))
         }
     }
 }
 
-fn is_filename(entry: &Path, name: &str) -> Result<bool, Box<dyn Error>> {
+fn is_filename(entry: &Path, name: &str) -> Result<bool, MediatimerError> {
     let mut entry = entry.to_path_buf();
     entry.set_extension("");
     if let Some(file_name) = entry.file_name() {
@@ -285,30 +480,36 @@ fn is_filename(entry: &Path, name: &str) -> Result<bool, Box<dyn Error>> {
     Ok(false)
 }
 
-fn dir_contains_url(path: PathBuf) -> Result<bool, Box<dyn Error>> {
+/// Finds the file holding the URL in the autoplay directory, renaming it to `url.mt`. A dry
+/// run leaves it as it is and returns its current name.
+fn dir_url_file(path: &Path, dry_run: DryRun) -> Result<Option<PathBuf>, MediatimerError> {
     if path.exists() {
-        let mut url_exists = false;
+        let mut url_file = None;
         // read the directory
         for entry in path.read_dir()?.flatten() {
             let entry_is_filename = is_filename(&entry.path(), "url")?; 
             if entry_is_filename {
+                if dry_run == DryRun::Yes {
+                    url_file = Some(entry.path());
+                    continue;
+                }
                 // rename the entry to comply with our suffix
                 let original_name = entry.path();
                 let mut entry_path = entry.path();
                 entry_path.set_file_name("url");
                 entry_path.set_extension("mt");
                 // rename the file
-                if fs::rename(original_name, entry_path).is_ok() {
-                    url_exists = true;
+                if fs::rename(original_name, &entry_path).is_ok() {
+                    url_file = Some(entry_path);
                 } else {
-                    //display error
-                    display_error_with_message("Failed to rename file containing URL. Please check permissions.");
+                    // carry on without the URL, the rest of the autoplay directory is still used
+                    logw!("Failed to rename file containing URL. Please check permissions.");
                 }
             }
         }
-        return Ok(url_exists);
+        return Ok(url_file);
     }
-    Ok(false)
+    Ok(None)
 }
 
 fn is_dirname(path: &Path, name: &str) -> bool {
@@ -324,6 +525,69 @@ fn is_dirname(path: &Path, name: &str) -> bool {
     }
 }
 
+/// How many times to look for the storage device holding the task's file. Drives can take a
+/// few seconds to be mounted after boot.
+const UUID_MATCH_ATTEMPTS: u32 = 5;
+const UUID_MATCH_DELAY: Duration = Duration::from_secs(2);
+
+/// Finds the storage device with the saved UUID and moves the file path onto its current
+/// mount point, as the device name in "/media/{username}/device-name" can change between
+/// insertions.
+fn repair_file_path(file: &Path, uuid: &str, dry_run: DryRun) -> Result<PathBuf, MediatimerError> {
+    // a dry run only looks at the drives already mounted, so there is nothing to wait for
+    let attempts = match dry_run {
+        DryRun::Yes => 1,
+        DryRun::No => UUID_MATCH_ATTEMPTS
+    };
+    let mut attempt = 1;
+    let mount_path = loop {
+        match match_uuid(uuid) {
+            Ok(mount_path) => break mount_path,
+            Err(e) if attempt < attempts => {
+                logw!("{}, retrying ({}/{})", e, attempt, attempts);
+                attempt += 1;
+                thread::sleep(UUID_MATCH_DELAY);
+            },
+            Err(e) => {
+                loge!("Could not match UUID and identify mount path");
+                return Err(e.with_setting("MT_UUID", uuid).with_path(file));
+            }
+        }
+    };
+
+    // get the new device name from the mount path and replace the device name in the file path
+    let new_device = mount_path.components().nth(2).and_then(|d| d.as_os_str().to_str());
+    let file_device = file.components().nth(2).and_then(|d| d.as_os_str().to_str());
+    match (new_device, file_device, file.to_str()) {
+        (Some(new_device_str), Some(file_device_str), Some(file_path_str)) => {
+            Ok(PathBuf::from(file_path_str.replace(file_device_str, new_device_str)))
+        },
+        _ => {
+            let failure_message = "Failed to replace file path with new device name";
+            loge!("{}", failure_message);
+            Err(MediatimerError::Mount(String::from(failure_message)).with_path(file))
+        }
+    }
+}
+
+/// Everything read from the autoplay directory or the config file that decides what the
+/// device plays and when
+struct Config {
+    model: Model,
+    task: Task,
+    schedule: AdvancedSchedule,
+    wait_for_time_sync: bool,
+    time_sync_timeout: u64,
+    timings: Vec<Weekday>,
+    seasons: Vec<Season>,
+    interrupts: Vec<InterruptConfig>,
+    cron_start: Vec<CronSchedule>,
+    cron_stop: Vec<CronSchedule>,
+    location: Option<Location>,
+}
+
+/// Loads the config the device runs with.
+///
 /// First the statement checks if a URL is present in a text file inside the autoplay directory
 /// Next the statement checks if the autoplay path exists
 ///
@@ -333,19 +597,10 @@ fn is_dirname(path: &Path, name: &str) -> bool {
 /// Lastly if the autoplay directory is not present on the mounted storage device, then the 
 /// Media Timer config variables are imported. These variables are set via the `mediatimer` 
 /// program.
-fn main() -> Result<(), Box<dyn Error>> {
-
-    // initialise the app
-    let app = App::default();
-
-    // initialise loggers
-    if let Err(e) = setup_logger() {
-        loge!("Logger could not be initialised: {}", e);
-    }
-
-    logi!("Initialising");
-    logi!("Loggers initialised");
-
+///
+/// A dry run leaves the devices as they are: only drives that are already mounted are read
+/// and no logs are copied to them.
+fn load_config(dry_run: DryRun) -> Result<Config, MediatimerError> {
     // Preset model to "pro" version so that all features are enabled if the model details 
     // cannot be found
     let mut model: Model = Model::Pro;
@@ -363,15 +618,27 @@ fn main() -> Result<(), Box<dyn Error>> {
     logi!("Model selected: {}", &model);
 
     // this will mount all of the drives automatically using udisksctl
-    let identified_drives = identify_mounted_drives();
+    let identified_drives = match dry_run {
+        DryRun::Yes => mount::list_mounted_drives(),
+        DryRun::No => identify_mounted_drives()
+    };
     let mut mounted_drives = Vec::new();
-    let _ = match identified_drives {
+    match identified_drives {
         Ok(drives) => mounted_drives = drives,
         Err(e) => {
             logw!("No storage devices identified, Error: {}", e);
         }
     };
 
+    // technicians collect the logs by putting a collect-logs file on a USB drive
+    for drive in mounted_drives.iter().filter(|_| dry_run == DryRun::No) {
+        match loggers::export_logs(drive) {
+            Ok(Some(export_dir)) => logi!("Logs copied to {}", export_dir.display()),
+            Ok(None) => {},
+            Err(e) => logw!("Failed to copy logs to {}: {}", drive.display(), e)
+        }
+    }
+
     // set up task vars
     let mut file = PathBuf::new();
     let mut web_url = String::with_capacity(0);
@@ -380,6 +647,14 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut proc_type = ProcType::Video;
     let mut auto_loop = Autoloop::No;
     let mut schedule = AdvancedSchedule::No;
+    let mut wait_for_time_sync = false;
+    let mut time_sync_timeout: u64 = 120;
+    let mut seasons: Vec<Season> = Vec::new();
+    let mut interrupt_configs: Vec<InterruptConfig> = Vec::new();
+    let mut cron_start: Vec<CronSchedule> = Vec::new();
+    let mut cron_stop: Vec<CronSchedule> = Vec::new();
+    let mut latitude: Option<f64> = None;
+    let mut longitude: Option<f64> = None;
     let mut monday: Weekday = Weekday::Monday(Vec::with_capacity(2));
     let mut tuesday: Weekday = Weekday::Tuesday(Vec::with_capacity(2));
     let mut wednesday: Weekday = Weekday::Wednesday(Vec::with_capacity(2));
@@ -390,26 +665,23 @@ fn main() -> Result<(), Box<dyn Error>> {
 
 
     let mut autoplay_path = PathBuf::new();
-    let mut url_path = PathBuf::new();
     if mounted_drives.len() == 1 {
         autoplay_path = PathBuf::from(&mounted_drives[0]);
         autoplay_path.push("autoplay");
-        url_path = autoplay_path.clone();
-        url_path.push("url.mt");
     }
 
 
 
-    let dir_contains_url = dir_contains_url(autoplay_path.clone())?;
+    let url_file = dir_url_file(&autoplay_path, dry_run)?;
     // First the statement checks if a URL is present in a text file inside the autoplay directory
-    if dir_contains_url  {
+    if let Some(url_path) = url_file {
         logi!("Reading URL from autoplay file path");
         // read the file at url_path
-        let file = fs::File::open(&url_path).expect("Failed to open URL file");
+        let file = fs::File::open(&url_path)
+            .map_err(|e| MediatimerError::Config(format!("Failed to open URL file: {}", e)).with_path(&url_path))?;
         let reader = BufReader::new(file);
-        let lines: Vec<String> = reader.lines().filter_map(|l| l.ok()).filter(|l| l.contains("https")).collect::<Vec<String>>();
-        let url_format_correct = url_format_correct(&lines[0])?; 
-        if !lines.is_empty() && url_format_correct {
+        let lines: Vec<String> = reader.lines().map_while(Result::ok).filter(|l| l.contains("https")).collect::<Vec<String>>();
+        if !lines.is_empty() && url_format_correct(&lines[0])? {
             web_url = lines[0].clone();
             proc_type = ProcType::Web;
             schedule = AdvancedSchedule::No;
@@ -420,7 +692,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Next the statement checks if the autoplay path exists
     } else if is_dirname(autoplay_path.as_path(), "autoplay") {
         // check if files are images or (audio/video) 
-        let files = fs::read_dir(&autoplay_path)?.map(|i| i.unwrap()).collect::<Vec<_>>();
+        let files = fs::read_dir(&autoplay_path)?.flatten().collect::<Vec<_>>();
         if files.len() == 1 {
             // This process checks the first file available inside the autoplay directory. It 
             // ascertains whether the file is video or audio media and sets the "proc_type" 
@@ -432,7 +704,8 @@ fn main() -> Result<(), Box<dyn Error>> {
                 .arg("-show_entries")
                 .arg("stream=codec_type")
                 .arg(files[0].path())
-                .output()?;
+                .output()
+                .map_err(|e| MediatimerError::Probe(format!("Failed to run ffprobe: {}", e)).with_path(&files[0].path()))?;
             let probe_string = String::from_utf8_lossy(&probe_text.stdout);
             
             let media_re = Regex::new(r"\scodec_type=(?<media>\w+)\b")?;
@@ -453,7 +726,9 @@ fn main() -> Result<(), Box<dyn Error>> {
                     logw!("Media name could not be captured in regex")
                 }
             } else {
-                logw!("Media codec could not be captured in regex")
+                logw!("Media codec could not be captured in regex");
+                return Err(MediatimerError::Probe(format!("Could not identify the media in {}", files[0].path().display()))
+                    .with_path(&files[0].path()));
             }
         } else {
             // multiple files are available so use slideshow proc
@@ -465,52 +740,65 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Media Timer config variables are imported. These variables are set via the `mediatimer` 
     // program.
     } else {
-        let username = whoami::username();
-        let env_dir_path: PathBuf =["/home/", &username, ".mediatimer_config/vars"].iter().collect();
+        let env_dir_path = config_path();
 
         if dotenvy::from_path_override(env_dir_path.as_path()).is_err() {
             eprintln!("Cannot find env vars at path: {}", env_dir_path.display());
             loge!("Cannot find env vars at path");
-            display_error_with_message("Could not find config file, please run mediatimer to set up this program.");    
+            return Err(MediatimerError::Config(String::from("Could not find config file, please run mediatimer to set up this program."))
+                .with_path(&env_dir_path));
         }
 
         for (key, value) in env::vars() {
-            match key.as_str() {
-                "MT_PROCTYPE" => { 
-                    proc_type = match value.as_str() {
-                        "video" => ProcType::Video,
-                        "audio" => ProcType::Audio,
-                        "image" => ProcType::Image,
-                        "slideshow" => ProcType::Slideshow,
-                        "web" => ProcType::Web,
-                        "browser" => ProcType::Browser,
-                        "executable" => ProcType::Executable,
-                        &_ => ProcType::Video
-                    }
-                },
-                "MT_AUTOLOOP" => auto_loop = match value.as_str() {
-                    "true" => Autoloop::Yes,
-                    "false" => Autoloop::No,
-                    &_ => Autoloop::No
-                },
-                "MT_FILE" => file.push(value.as_str()),
-                "MT_URL" => web_url.push_str(value.as_str()),
-                "MT_UUID" => uuid.push_str(value.as_str()),
-                "MT_SLIDE_DELAY" => slide_delay = value.parse::<u32>()?,
-                "MT_SCHEDULE" => schedule = match value.as_str() {
-                    "true" => AdvancedSchedule::Yes,
-                    "false" => AdvancedSchedule::No,
-                    &_ => AdvancedSchedule::No
-                },
-                "MT_MONDAY" => monday = to_weekday(value, Weekday::Monday(Vec::new()), schedule.clone())?,
-                "MT_TUESDAY" => tuesday = to_weekday(value, Weekday::Tuesday(Vec::new()), schedule.clone())?,
-                "MT_WEDNESDAY" => wednesday = to_weekday(value, Weekday::Wednesday(Vec::new()), schedule.clone())?,
-                "MT_THURSDAY" => thursday = to_weekday(value, Weekday::Thursday(Vec::new()), schedule.clone())?,
-                "MT_FRIDAY" => friday = to_weekday(value, Weekday::Friday(Vec::new()), schedule.clone())?,
-                "MT_SATURDAY" => saturday = to_weekday(value, Weekday::Saturday(Vec::new()), schedule.clone())?,
-                "MT_SUNDAY" => sunday = to_weekday(value, Weekday::Sunday(Vec::new()), schedule.clone())?,
-                _ => {}
-            }
+            // kept so the error can say which setting could not be read
+            let shown = value.clone();
+            let apply = || -> Result<(), MediatimerError> {
+                match key.as_str() {
+                    "MT_PROCTYPE" => proc_type = to_proc_type(&value),
+                    "MT_AUTOLOOP" => auto_loop = match value.as_str() {
+                        "true" => Autoloop::Yes,
+                        "false" => Autoloop::No,
+                        &_ => Autoloop::No
+                    },
+                    "MT_FILE" => file.push(value.as_str()),
+                    "MT_URL" => web_url.push_str(value.as_str()),
+                    "MT_UUID" => uuid.push_str(value.as_str()),
+                    "MT_SLIDE_DELAY" => slide_delay = value.parse::<u32>()?,
+                    // an unknown level keeps the level already set rather than stopping
+                    "MT_LOG_LEVEL" => if let Err(e) = loggers::set_level(&value) {
+                        logw!("{}", e);
+                    },
+                    "MT_SCHEDULE" => schedule = match value.as_str() {
+                        "true" => AdvancedSchedule::Yes,
+                        "false" => AdvancedSchedule::No,
+                        &_ => AdvancedSchedule::No
+                    },
+                    // an unknown timezone falls back to the system timezone rather than stopping
+                    "MT_TIMEZONE" => match timezone::parse(&value) {
+                        Ok(tz) => timezone::set(tz),
+                        Err(e) => logw!("{}. Please use an IANA timezone name such as Europe/London. Using the system timezone.", e)
+                    },
+                    "MT_CRON_START" => cron_start = to_cron(&value)?,
+                    "MT_CRON_STOP" => cron_stop = to_cron(&value)?,
+                    "MT_LATITUDE" => latitude = Some(value.parse::<f64>()?),
+                    "MT_LONGITUDE" => longitude = Some(value.parse::<f64>()?),
+                    "MT_SCOPE" => scope::set_enabled(value.as_str() == "true"),
+                    "MT_TIME_SYNC" => wait_for_time_sync = value.as_str() == "true",
+                    "MT_TIME_SYNC_TIMEOUT" => time_sync_timeout = value.parse::<u64>()?,
+                    "MT_MONDAY" => monday = to_weekday(value, Weekday::Monday(Vec::new()), schedule.clone())?,
+                    "MT_TUESDAY" => tuesday = to_weekday(value, Weekday::Tuesday(Vec::new()), schedule.clone())?,
+                    "MT_WEDNESDAY" => wednesday = to_weekday(value, Weekday::Wednesday(Vec::new()), schedule.clone())?,
+                    "MT_THURSDAY" => thursday = to_weekday(value, Weekday::Thursday(Vec::new()), schedule.clone())?,
+                    "MT_FRIDAY" => friday = to_weekday(value, Weekday::Friday(Vec::new()), schedule.clone())?,
+                    "MT_SATURDAY" => saturday = to_weekday(value, Weekday::Saturday(Vec::new()), schedule.clone())?,
+                    "MT_SUNDAY" => sunday = to_weekday(value, Weekday::Sunday(Vec::new()), schedule.clone())?,
+                    key if key.starts_with("MT_INTERRUPT_") => to_interrupt(&mut interrupt_configs, key, value)?,
+                    key if key.starts_with("MT_SEASON_") => to_season(&mut seasons, key, value, schedule.clone())?,
+                    _ => {}
+                }
+                Ok(())
+            };
+            apply().map_err(|e| e.with_setting(&key, &shown))?;
         }
         // every ProcType requires a file, except Web
         // This statement checks to see if the file exists at the path saved in the mediatimer 
@@ -519,198 +807,221 @@ fn main() -> Result<(), Box<dyn Error>> {
         // program if necessary. 
         if proc_type != ProcType::Web && !file.clone().as_path().exists() {
             // match the uuid and change the file path if necessary
-            if let Ok(mount_path) = match_uuid(&uuid) {
-
-                let failure_message = "Failed to replace file path with new device name";
-                // get the new device name from the mount path
-                if let Some(new_device) = mount_path.components().nth(2) {
-                    if let Some(new_device_str) = new_device.as_os_str().to_str() {
-                        // replace the device name in the file_path "/media/{username}/device-name"   
-                        if let Some(file_path_str) = file.to_str() {
-                            if let Some(file_device) = file.components().nth(2) { 
-                                if let Some(file_device_str) = file_device.as_os_str().to_str() {
-
-                                    file = PathBuf::from(file_path_str.replace(file_device_str, new_device_str));
-                                } else {
-                                    loge!("{}", failure_message);
-                                    display_error_with_message(failure_message);    
-                                }
-                            } else {
-                                loge!("{}", failure_message);
-                                display_error_with_message(failure_message);    
-                            }
-                        } else {
-                            loge!("{}", failure_message);
-                            display_error_with_message(failure_message);    
-                        }
-                    } else {
-                        loge!("{}", failure_message);
-                        display_error_with_message(failure_message);    
-                    }
-                } else {
-                    loge!("{}", failure_message);
-                    display_error_with_message(failure_message);    
-                }
-            } else {
-                loge!("Could not match UUID and identify mount path");
-                display_error_with_message("Could not match storage device UUID and identify mount path.");    
-            }
+            file = repair_file_path(&file, &uuid, dry_run)?;
         }
     } 
 
     let timings = vec![monday, tuesday, wednesday, thursday, friday, saturday, sunday]; 
 
-    let timings_clone = timings.clone();
+    let location = match (latitude, longitude) {
+        (Some(latitude), Some(longitude)) => Some(Location { latitude, longitude }),
+        _ => None
+    };
+    seasons.sort_by(|a, b| a.name.cmp(&b.name));
+    interrupt_configs.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(Config {
+        task: Task::new(model.clone(), proc_type, auto_loop, file, slide_delay, web_url),
+        model,
+        schedule,
+        wait_for_time_sync,
+        time_sync_timeout,
+        timings,
+        seasons,
+        interrupts: interrupt_configs,
+        cron_start,
+        cron_stop,
+        location,
+    })
+}
 
-    let task: Arc<Mutex<Task>> = Arc::new(Mutex::new(Task::new(model, proc_type, auto_loop, file, slide_delay, web_url)));
+/// Builds the timetable from the weekly schedule, seasons, cron entries and interrupts in
+/// the config, along with the interrupts the timetable's interrupt events refer to.
+fn build_timetable(config: &Config) -> Result<(Timetable, Vec<Interrupt>), MediatimerError> {
+    let mut timetable = Timetable::new(timezone::get(), config.location);
+    if let Err(e) = timetable.add_weekdays(&config.timings) {
+        loge!("Could not parse schedule: {}", e);
+        return Err(e);
+    }
+    for season in config.seasons.iter() {
+        if let Err(e) = timetable.add_season(season) {
+            loge!("Could not parse season: {}", e);
+            return Err(e);
+        }
+    }
+    if !config.seasons.is_empty() && config.timings.iter().any(|d| !d.schedule().is_empty()) {
+        logw!("Seasonal schedules are configured, so the MT_<DAY> schedule is not used");
+    }
+    let repeats = config.timings.iter()
+        .map(|d| d.schedule())
+        .chain(config.seasons.iter().flat_map(|s| s.days.iter().map(|d| d.schedule())))
+        .any(|s| s.iter().any(|(_, end)| end.contains(" every ")));
+    if repeats && matches!(config.task.auto_loop, Autoloop::Yes) {
+        logw!("Repeating windows are configured with MT_AUTOLOOP, so each repeat loops until the next");
+    }
+    timetable.add_cron(Action::Start, &config.cron_start);
+    timetable.add_cron(Action::Stop, &config.cron_stop);
+
+    let mut interrupts = Vec::with_capacity(config.interrupts.len());
+    for interrupt in config.interrupts.iter().cloned() {
+        let Some(at) = interrupt.at else {
+            logw!("Interrupt {} has no time set and is skipped, please set MT_INTERRUPT_{}_AT", interrupt.name, interrupt.name.to_uppercase());
+            continue;
+        };
+        if interrupt.duration.is_none() && !matches!(interrupt.proc_type, ProcType::Video | ProcType::Audio | ProcType::Executable) {
+            logw!("Interrupt {} has no duration and will play until pre-empted", interrupt.name);
+        }
+        timetable.add_interrupt(InterruptSchedule { at, duration: interrupt.duration });
+        interrupts.push(Interrupt {
+            name: interrupt.name,
+            priority: interrupt.priority,
+            task: Arc::new(Mutex::new(Task::new(config.model.clone(), interrupt.proc_type, Autoloop::No, interrupt.file, config.task.slide_delay, interrupt.web_url))),
+            run_until_exit: interrupt.duration.is_none(),
+        });
+    }
+    Ok((timetable, interrupts))
+}
+
+/// Loads the config and plays it. This only returns if the device could not be started.
+fn start(app: &App) -> Result<Infallible, MediatimerError> {
+    let config = load_config(DryRun::No)?;
 
-    // set up scheduler
-    let mut scheduler = Scheduler::new();
-    if schedule == AdvancedSchedule::Yes {
+    if config.schedule == AdvancedSchedule::Yes {
         // create then start the background after the task is created
         if let Err(e) = background::make() {
             loge!("Failed to make background: {}", e);
         }
 
-        if let Err(e) = background::run(Arc::clone(&app.task_list)) {
-            loge!("Failed to run background: {}", e);
+        show_background(&app.playback);
+
+        // devices without an RTC battery boot with a stale clock, so keep showing the
+        // background until the time can be trusted
+        if config.wait_for_time_sync {
+            time_sync::wait_for_sync(Duration::from_secs(config.time_sync_timeout));
         }
 
-        // use the full scheduler and run the task at certain times
-        for day in timings_clone.iter() {
-            let day_name = match day {
-                Weekday::Monday(_) => Interval::Monday,
-                Weekday::Tuesday(_) => Interval::Tuesday,
-                Weekday::Wednesday(_) => Interval::Wednesday,
-                Weekday::Thursday(_) => Interval::Thursday,
-                Weekday::Friday(_) => Interval::Friday, 
-                Weekday::Saturday(_) => Interval::Saturday, 
-                Weekday::Sunday(_) => Interval::Sunday 
+        let (timetable, interrupt_list) = build_timetable(&config)?;
+        let task: Arc<Mutex<Task>> = Arc::new(Mutex::new(config.task));
 
-            };
-            let timing_vec = match day {
-                Weekday::Monday(t) => t,
-                Weekday::Tuesday(t) => t,
-                Weekday::Wednesday(t) => t,
-                Weekday::Thursday(t) => t,
-                Weekday::Friday(t) => t, 
-                Weekday::Saturday(t) => t, 
-                Weekday::Sunday(t) => t 
-            };
+        let timetable = Arc::new(timetable);
+        let interrupts = Interrupts::new(interrupt_list, Arc::clone(&timetable), Arc::clone(&task), Arc::clone(&app.playback));
 
-            fn get_timing_as_hms(value: &str) -> (u32, u32, u32) {
-                let i = value.split(":").map(|t| t.parse::<u32>().unwrap()).collect::<Vec<u32>>();
-                // function must return a date format string
-                if i.len() == 2 {
-                    (i[0], i[1], 0_u32) 
-                } else {
-                    (i[0], i[1], i[2]) 
-                }
-            }
-
-            // iterates through each timing for the day
-            for timing in timing_vec.iter() {
-                let timing_clone = timing.clone();
-                let task_clone = Arc::clone(&task);
-                let task_list_clone = Arc::clone(&app.task_list);
-                let task_list_clone_2 = Arc::clone(&app.task_list);
-
-                // check if day is today 
-                let local = Local::now();
-                let day_today = format!("{}", local.format("%A"));
-
-
-                let timing_day = day.as_str();
-                if day_today.to_lowercase() == timing_day.to_lowercase() {
-                    let date_string = format!("{}", local.format("%Y:%m:%d:%H:%M:%S"));
-                    let date_nums: Vec<u32> = date_string.split(":").map(|i| i.parse::<u32>()).filter_map(|x| Some(x)?.ok()).collect::<Vec<u32>>();
-                    let year_num = date_nums[0] as i32;
-                    let month_num = date_nums[1];
-                    let day_num = date_nums[2];
-                    let _hour_num = date_nums[3];
-                    let _min_num = date_nums[4];
-                    let _sec_num = date_nums[5];
-
-                    let (start_hour, start_min, start_sec) = get_timing_as_hms(&timing.0);
-
-                    if let Some(start_time) = Local.with_ymd_and_hms(
-                        year_num, month_num, day_num, 
-                        start_hour, start_min, start_sec).single() {
-
-                        let (end_hour, end_min, end_sec) = get_timing_as_hms(&timing.1);
-
-                        if let Some(end_time) = Local.with_ymd_and_hms(
-                            year_num, month_num, day_num, 
-                            end_hour, end_min, end_sec).single() {
-
-                            let local_timestamp = local.timestamp(); 
-                            // if &timing.0 is less 
-                            if local_timestamp > start_time.timestamp() && local_timestamp < end_time.timestamp() {
-
-                                let task_list_clone_3 = Arc::clone(&app.task_list);
-                                let task_clone_2 = Arc::clone(&task);
-                                if let Err(e) = run_task(task_list_clone_3.clone(), task_clone_2.clone(), &timing_clone.0) {
-                                    loge!("Failed to run task: {}", e);
-                                    display_error_with_message("Failed to run task!");    
-                                }
-                            }
-                        } else {
-                            loge!("Could not parse stop time");
-                            display_error_with_message("Could not parse stop time!");    
-                        }
-                    } else {
-                        loge!("Could not parse start time");
-                        display_error_with_message("Could not parse start time!");    
+        // use the full scheduler and run the task at certain times. If the device starts part
+        // way through a window the scheduler starts the task straight away.
+        scheduler::run(&timetable, |event| {
+            match event.action {
+                Action::Start | Action::Stop if interrupts.is_active() => {
+                    logi!("Interrupt playing, {} will be applied when it ends", event);
+                },
+                Action::Interrupt(index) => {
+                    if let Err(e) = interrupts.begin(index) {
+                        loge!("Failed to run interrupt: {}", e);
+                    }
+                },
+                Action::InterruptEnd(index) => {
+                    if let Err(e) = interrupts.end(index) {
+                        loge!("Failed to end interrupt: {}", e);
+                        show_background(&app.playback);
+                    }
+                },
+                Action::Start => {
+                    if let Err(e) = run_task(&app.playback, &task, Some(event.window_start)) {
+                        loge!("Failed to run task: {}", e);
+                        show_background(&app.playback);
                     }
+                },
+                Action::Stop => {
+                    if let Err(e) = app.playback.stop() {
+                        loge!("Failed to stop task: {}", e);
+                        show_background(&app.playback);
+                    }
+                    notify::status("Showing the background");
                 }
-
-                scheduler.every(day_name)
-                    .at(&timing.0)
-                    .run(move || { 
-                        if let Err(e) = run_task(task_list_clone.clone(), task_clone.clone(), &timing_clone.0) {
-                            loge!("Failed to run task:{}", e);
-                            display_error_with_message("Failed to run task!");    
-                        }
-                    });
-
-                scheduler.every(day_name)
-                    .at(&timing.1)
-                    // unused Result type in closure
-                    .run(move || { 
-                        if let Err(e) = stop_task(task_list_clone_2.clone()) {
-                            loge!("Failed to stop task:{}", e);
-                            display_error_with_message("Failed to stop task!"); 
-                        }
-                    });
             }
-        }
-        loop {
-            scheduler.run_pending();
-            thread::sleep(Duration::from_millis(10));
-        }
+        });
     } else {
         // run the task now
-        let task_clone = Arc::clone(&task); 
-        let task_list_clone = Arc::clone(&app.task_list);
-        if let Err(e) = run_task(task_list_clone, task_clone, "") {
+        run_task(&app.playback, &Mutex::new(config.task), None)?;
+        // nothing is upcoming, but this clears any error left from an earlier attempt
+        status::write_upcoming(&[]);
+        notify::ready();
+        // nothing else is scheduled, so wait without waking
+        notify::idle();
+    }
+}
 
-            loge!("Failed to run task:{}", e);
-            display_error_with_message("Failed to run task!");    
+fn main() -> Result<(), MediatimerError> {
+    // the local date is used rather than timezone::now(), which would fix the timezone before
+    // MT_TIMEZONE is read from the config
+    if let Some(options) = proof::Options::from_args(env::args().skip(1), || Local::now().date_naive())? {
+        return proof::print_summary(options);
+    }
+    let preview = preview::Options::from_args(env::args().skip(1))?;
+
+    // initialise the app
+    let app = App::default();
+
+    // initialise loggers
+    if let Err(e) = setup_logger() {
+        loge!("Logger could not be initialised: {}", e);
+    }
+
+    logi!("Initialising");
+    logi!("Loggers initialised");
+
+    // a preview only prints, so errors are reported on the command line as normal
+    if let Some(options) = preview {
+        return preview::print(&load_config(DryRun::Yes)?, options);
+    }
+
+    notify::start_watchdog(Arc::clone(&app.playback));
+    reaper::start();
+    if let Err(e) = shutdown::handle_signals(Arc::clone(&app.playback)) {
+        loge!("Failed to handle stop signals, players may be left running on stop: {}", e);
+    }
+    loop {
+        let Err(e) = start(&app);
+        loge!("{}", e);
+        match Policy::current() {
+            // the non-zero exit lets systemd restart the service once the error is dismissed
+            Policy::Interactive => {
+                display_error(&e);
+                return Err(e);
+            },
+            // nobody is there to dismiss the error, so show it full screen and keep retrying
+            Policy::Headless => {
+                status::write_error(&e, HEADLESS_RETRY);
+                // the service is up, it is just showing the error until the next attempt
+                notify::ready();
+                notify::status(&format!("Error {}, retrying in {}s", e, HEADLESS_RETRY.as_secs()));
+                // owned by the playback controller so that it is closed if the service is stopped
+                if let Err(e) = app.playback.show_error(&e) {
+                    loge!("{}", e);
+                }
+                notify::alive();
+                thread::sleep(HEADLESS_RETRY);
+                notify::alive();
+                app.playback.stop_all();
+                logi!(MT_EVENT = "restart"; "Retrying start up after {}", e.code());
+            }
         }
-        loop {
-            std::thread::sleep(Duration::from_secs(60));
-        };
     }
 }
 
 #[cfg(test)]
+// the older tests assert on constants in their match arms
+#[allow(clippy::assertions_on_constants, clippy::unnecessary_cast)]
 mod tests {
     use super::*;
     use std::path::PathBuf;
     use std::sync::{Arc, Mutex};
-    use chrono::Local;
     use std::fs;
     use tempfile::tempdir;
+    use crate::launcher::mock::{
+        MockLauncher,
+        Outcome
+    };
+    use crate::playback::State;
     use std::os::unix::fs::PermissionsExt;
 
     // Test the Weekday enum functionality
@@ -814,24 +1125,88 @@ mod tests {
         }
     }
 
-    // Test functionality of the RunningTask struct
     #[test]
-    fn test_running_task_new() {
+    fn test_to_weekday_solar_schedule() {
+        let value = "sunset-00:30-23:00:00, 06:00:00-sunrise+01:00".to_string();
+        let result = to_weekday(value, Weekday::Friday(Vec::new()), AdvancedSchedule::Yes);
 
-        let test_task = Task::new(
-            Model::Pro,
-            ProcType::Browser,
-            Autoloop::No,
-            PathBuf::from("/"),
-            5,
-            String::new()
-        );
+        match result.unwrap() {
+            Weekday::Friday(schedule) => {
+                assert_eq!(schedule[0], ("sunset-00:30".to_string(), "23:00:00".to_string()));
+                assert_eq!(schedule[1], ("06:00:00".to_string(), "sunrise+01:00".to_string()));
+            },
+            _ => panic!("Incorrect weekday returned"),
+        }
+    }
+
+    #[test]
+    fn test_to_weekday_repeat_schedule() {
+        let value = "10:00:00-17:00:00 every 20m for 5m".to_string();
+        let result = to_weekday(value, Weekday::Saturday(Vec::new()), AdvancedSchedule::Yes);
+
+        match result.unwrap() {
+            Weekday::Saturday(schedule) => {
+                assert_eq!(schedule[0], ("10:00:00".to_string(), "17:00:00 every 20m for 5m".to_string()));
+            },
+            _ => panic!("Incorrect weekday returned"),
+        }
+    }
+
+    #[test]
+    fn test_to_interrupt() {
+        let mut interrupts = Vec::new();
+        to_interrupt(&mut interrupts, "MT_INTERRUPT_NOON_AT", "12:00:00".to_string()).unwrap();
+        to_interrupt(&mut interrupts, "MT_INTERRUPT_NOON_FILE", "/tmp/announce.mp4".to_string()).unwrap();
+        to_interrupt(&mut interrupts, "MT_INTERRUPT_NOON_DURATION", "90".to_string()).unwrap();
+        to_interrupt(&mut interrupts, "MT_INTERRUPT_LAUNCH_AT", "2026-11-01 18:00:00".to_string()).unwrap();
+        to_interrupt(&mut interrupts, "MT_INTERRUPT_LAUNCH_PRIORITY", "5".to_string()).unwrap();
+
+        assert_eq!(interrupts.len(), 2);
+        assert_eq!(interrupts[0].name, "NOON");
+        assert_eq!(interrupts[0].at, Some(InterruptTime::Daily(chrono::NaiveTime::from_hms_opt(12, 0, 0).unwrap())));
+        assert_eq!(interrupts[0].file, PathBuf::from("/tmp/announce.mp4"));
+        assert_eq!(interrupts[0].duration, Some(TimeDelta::seconds(90)));
+        assert_eq!(interrupts[0].priority, 1);
+        assert_eq!(interrupts[1].name, "LAUNCH");
+        assert!(matches!(interrupts[1].at, Some(InterruptTime::Once(_))));
+        assert_eq!(interrupts[1].priority, 5);
+    }
+
+    #[test]
+    fn test_to_season() {
+        let mut seasons = Vec::new();
+        to_season(&mut seasons, "MT_SEASON_SUMMER_2026_FROM", "2026-04-01".to_string(), AdvancedSchedule::Yes).unwrap();
+        to_season(&mut seasons, "MT_SEASON_SUMMER_2026_MONDAY", "10:00:00-18:00:00".to_string(), AdvancedSchedule::Yes).unwrap();
+        to_season(&mut seasons, "MT_SEASON_WINTER_UNTIL", "2026-12-31".to_string(), AdvancedSchedule::Yes).unwrap();
+
+        assert_eq!(seasons.len(), 2);
+        assert_eq!(seasons[0].name, "SUMMER_2026");
+        assert_eq!(seasons[0].valid_from, NaiveDate::from_ymd_opt(2026, 4, 1));
+        assert_eq!(seasons[0].days.len(), 1);
+        assert_eq!(seasons[1].name, "WINTER");
+        assert_eq!(seasons[1].valid_until, NaiveDate::from_ymd_opt(2026, 12, 31));
+    }
 
+    #[test]
+    fn test_timing_format_correct() {
+        assert!(timing_format_correct("08:00:00-12:00:00").unwrap());
+        assert!(timing_format_correct("sunset-00:30-23:00:00").unwrap());
+        assert!(timing_format_correct("sunrise-sunset").unwrap());
+        assert!(!timing_format_correct("25:00:00-12:00:00").unwrap());
+        assert!(!timing_format_correct("dusk-23:00:00").unwrap());
+        assert!(timing_format_correct("10:00:00-17:00:00 every 20m").unwrap());
+        assert!(timing_format_correct("10:00:00-17:00:00 every 20m for 5m").unwrap());
+        assert!(!timing_format_correct("10:00:00-17:00:00 every").unwrap());
+    }
 
+    // Test functionality of the RunningTask struct
+    #[test]
+    fn test_running_task_new() {
         let dummy_child = Command::new("echo").spawn().expect("Failed to create dummy process");
-        let task = RunningTask::new(dummy_child, false);
+        let task = RunningTask::new(dummy_child);
 
-        assert_eq!(task.background, false);
+        assert!(task.play.is_none());
+        assert!(task.scope.is_none());
         // We can't directly test the child process, but we can verify the struct was created
     }
 
@@ -840,42 +1215,55 @@ mod tests {
 
     #[test]
     fn test_run_and_stop_task() {
-
-        let video_proc = ProcType::Video;
-        let create_background = background::make();
-
-        let task_list = Arc::new(Mutex::new(Vec::new()));
-
         // Create a temporary test script
         let dir = tempdir().unwrap();
         let script_path = dir.path().join("test_script.sh");
         fs::write(&script_path, "#!/bin/sh\nsleep 10\n").unwrap();
         fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755)).unwrap();
 
-        let task = Arc::new(Mutex::new(Task::new(
+        let task = Mutex::new(Task::new(
                     Model::Pro,
                     ProcType::Executable,
                     Autoloop::No,
-                    script_path,
+                    script_path.clone(),
                     5,
                     String::new()
-        )));
-
+        ));
 
-        // Run the task
-        run_task(Arc::clone(&task_list), Arc::clone(&task), "");
 
-        // Give it a moment to start
-        thread::sleep(Duration::from_millis(500));
+        // Run the task, with a stand in for the script
+        let launcher = Arc::new(MockLauncher::new(Outcome::Run));
+        let playback = Playback::new(launcher.clone());
+        run_task(&playback, &task, None).unwrap();
+        assert_eq!(launcher.launched(), vec![vec!["sh".to_string(), script_path.to_string_lossy().to_string()]]);
 
         // Check task is running
-        assert_eq!(task_list.lock().unwrap().len(), 1);
+        assert_eq!(playback.state(), State::Playing);
 
-        // Stop the task
-        stop_task(Arc::clone(&task_list));
+        // Stop the task, the background is shown in its place
+        playback.stop().unwrap();
+        assert_eq!(playback.state(), State::Background);
+        assert_eq!(launcher.launched().len(), 2);
+        assert_eq!(launcher.launched()[1][0], "ffplay");
 
-        // Task list should be empty after stopping
-        assert_eq!(task_list.lock().unwrap().len(), 1);
+        playback.stop_all();
+    }
+
+    #[test]
+    fn test_preview_leaves_url_file() {
+        let drive = tempdir().unwrap();
+        let autoplay = drive.path().join("autoplay");
+        fs::create_dir(&autoplay).unwrap();
+        fs::write(autoplay.join("URL.txt"), "https://example.com\n").unwrap();
+
+        // a preview reads the file where it is
+        assert_eq!(dir_url_file(&autoplay, DryRun::Yes).unwrap(), Some(autoplay.join("URL.txt")));
+        assert!(autoplay.join("URL.txt").exists());
+        assert!(!autoplay.join("url.mt").exists());
+
+        assert_eq!(dir_url_file(&autoplay, DryRun::No).unwrap(), Some(autoplay.join("url.mt")));
+        assert!(!autoplay.join("URL.txt").exists());
+        assert_eq!(fs::read_to_string(autoplay.join("url.mt")).unwrap(), "https://example.com\n");
     }
 
     // Mock test for scheduler functionality
@@ -902,6 +1290,6 @@ mod tests {
     #[test]
     fn test_app_default() {
         let app = App::default();
-        assert_eq!(app.task_list.lock().unwrap().len(), 0);
+        assert_eq!(app.playback.state(), State::Idle);
     }
 }
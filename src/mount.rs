@@ -1,27 +1,18 @@
 use std::{
-    error::Error,
-    process::{
-        Command,
-        Stdio
-    },
+    process::Command,
     path::{
         PathBuf
     },
-    io::{
-        Error as IoError,
-        ErrorKind
-    }
 };
 
 use crate::{
     logi,
-    loge,
-    logw
+    logw,
+    error::MediatimerError
 };
 use log::{
     info,
-    warn,
-    error
+    warn
 };
 
 use regex::Regex;
@@ -65,135 +56,424 @@ impl Usb {
     }
 }
 
-pub fn identify_mounted_drives() -> Result<Vec<PathBuf>, Box<dyn Error>> {
+/// The commands used to find and mount storage devices. Tests substitute recorded output
+/// for the real commands.
+pub trait Devices {
+    /// `lsblk -l -o NAME,HOTPLUG`
+    fn list_hotplug(&self) -> Result<String, MediatimerError>;
+    /// `lsblk -P -o NAME,HOTPLUG,UUID,MOUNTPOINT`
+    fn list_uuids(&self) -> Result<String, MediatimerError>;
+    /// `udisksctl info -b <device>`
+    fn info(&self, device: &str) -> Result<String, MediatimerError>;
+    /// `udisksctl mount -b <device>`
+    fn mount(&self, device: &str) -> Result<String, MediatimerError>;
+}
+
+/// Queries the devices attached to this machine
+pub struct SystemDevices;
+
+fn stdout_of(command: &mut Command) -> Result<String, MediatimerError> {
+    let output = command.output()
+        .map_err(|e| MediatimerError::Mount(format!("Failed to run {}: {}", command.get_program().to_string_lossy(), e)))?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+impl Devices for SystemDevices {
+    fn list_hotplug(&self) -> Result<String, MediatimerError> {
+        stdout_of(Command::new("lsblk")
+            .arg("-l")
+            .arg("-o")
+            .arg("NAME,HOTPLUG"))
+    }
+
+    fn list_uuids(&self) -> Result<String, MediatimerError> {
+        stdout_of(Command::new("lsblk")
+            .arg("-P")
+            .arg("-o")
+            .arg("NAME,HOTPLUG,UUID,MOUNTPOINT"))
+    }
+
+    fn info(&self, device: &str) -> Result<String, MediatimerError> {
+        stdout_of(Command::new("udisksctl")
+            .arg("info")
+            .arg("-b")
+            .arg(device))
+    }
+
+    fn mount(&self, device: &str) -> Result<String, MediatimerError> {
+        stdout_of(Command::new("udisksctl")
+            .arg("mount")
+            .arg("-b")
+            .arg(device))
+    }
+}
+
+/// The hotplugged partitions listed by `lsblk -l -o NAME,HOTPLUG`
+fn hotplug_partitions(list: &str) -> Vec<Usb> {
+    let re = Regex::new(r"^sd[abc][1-4]$").unwrap();
+    let mut partitions = Vec::new();
+    for line in list.lines() {
+        let drive_info = line.split_whitespace().collect::<Vec<_>>();
+        if drive_info.len() == 2 && re.is_match(drive_info[0]) && drive_info[1] == "1" {
+            let drive = match drive_info[0] {
+                "sda1" => Usb::SDA1,
+                "sda2" => Usb::SDA2,
+                "sda3" => Usb::SDA3,
+                "sda4" => Usb::SDA4,
+                "sdb1" => Usb::SDB1,
+                "sdb2" => Usb::SDB2,
+                "sdb3" => Usb::SDB3,
+                "sdb4" => Usb::SDB4,
+                "sdc1" => Usb::SDC1,
+                "sdc2" => Usb::SDC2,
+                "sdc3" => Usb::SDC3,
+                "sdc4" => Usb::SDC4,
+                &_ => Usb::Unknown
+            };
+            partitions.push(drive);
+        }
+    }
+    partitions
+}
+
+/// The first mount point in `udisksctl info` output, or None if the partition is not mounted
+fn mount_point_from_info(info: &str) -> Option<PathBuf> {
+    info.lines()
+        .find_map(|line| line.trim().strip_prefix("MountPoints:"))
+        .map(|mount_point| mount_point.trim())
+        .filter(|mount_point| !mount_point.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The mount point from `udisksctl mount` output such as "Mounted /dev/sda1 at /media/pi/USB".
+/// Older versions of udisks end the line with a full stop.
+fn mount_point_from_mount(output: &str) -> Option<PathBuf> {
+    output.lines()
+        .find_map(|line| line.trim().strip_prefix("Mounted "))
+        .and_then(|line| line.split_once(" at "))
+        .map(|(_, mount_point)| mount_point.strip_suffix('.').unwrap_or(mount_point))
+        .filter(|mount_point| !mount_point.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Decodes the `\xNN` escapes lsblk uses for characters such as spaces and quotes
+fn unescape(value: &str) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'\\'
+            && tail.first() == Some(&b'x')
+            && let Some(hex) = tail.get(1..3)
+            && let Ok(decoded) = u8::from_str_radix(&String::from_utf8_lossy(hex), 16) {
+            bytes.push(decoded);
+            rest = &tail[3..];
+            continue;
+        }
+        bytes.push(byte);
+        rest = tail;
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+/// Reads one line of `lsblk -P` output, such as `NAME="sda1" UUID="1234-ABCD"`, into
+/// (column, value) pairs
+fn parse_pairs(line: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let mut rest = line.trim();
+    while let Some((key, value)) = rest.split_once("=\"") {
+        let Some((value, tail)) = value.split_once('"') else {
+            break;
+        };
+        pairs.push((key.trim().to_string(), unescape(value)));
+        rest = tail;
+    }
+    pairs
+}
+
+/// The mount point of the partition with `uuid` in `lsblk -P` output
+fn mount_point_for_uuid(list: &str, uuid: &str) -> Option<PathBuf> {
+    list.lines()
+        .map(parse_pairs)
+        .find(|pairs| pairs.iter().any(|(k, v)| k == "UUID" && v == uuid))
+        .and_then(|pairs| pairs.into_iter().find(|(k, _)| k == "MOUNTPOINT"))
+        .map(|(_, mount_point)| mount_point)
+        .filter(|mount_point| !mount_point.is_empty())
+        .map(PathBuf::from)
+}
+
+/// One line for each partition lsblk lists with a filesystem, saying whether it is removable
+/// and where it is mounted
+fn drive_summary(list: &str) -> Vec<String> {
+    list.lines()
+        .map(parse_pairs)
+        .filter_map(|pairs| {
+            let column = |name: &str| pairs.iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.clone())
+                .unwrap_or_default();
+            let uuid = column("UUID");
+            if uuid.is_empty() {
+                return None;
+            }
+            let removable = if column("HOTPLUG") == "1" { " (removable)" } else { "" };
+            let mount_point = column("MOUNTPOINT");
+            let mounted = if mount_point.is_empty() { String::from("not mounted") } else { format!("mounted at {}", mount_point) };
+            Some(format!("{}{} UUID {} {}", column("NAME"), removable, uuid, mounted))
+        })
+        .collect()
+}
+
+/// Lists the partitions the device can see, for the diagnostics page
+pub fn describe_drives() -> Result<Vec<String>, MediatimerError> {
+    Ok(drive_summary(&SystemDevices.list_uuids()?))
+}
+
+pub fn identify_mounted_drives() -> Result<Vec<PathBuf>, MediatimerError> {
+    find_mounted_drives(&SystemDevices, true)
+}
+
+/// Lists the hotplugged partitions that are already mounted, without mounting any others
+pub fn list_mounted_drives() -> Result<Vec<PathBuf>, MediatimerError> {
+    find_mounted_drives(&SystemDevices, false)
+}
+
+/// Finds the hotplugged partitions and returns their mount points. If `mount` is set any
+/// that are not yet mounted are mounted, otherwise they are skipped.
+fn find_mounted_drives(devices: &dyn Devices, mount: bool) -> Result<Vec<PathBuf>, MediatimerError> {
     logi!("Identifying mounted drives");
     let mut mounts = Vec::with_capacity(2);
-    // find out if any drives mounted, otherwise default to /home/username
-    let all_drives = Command::new("lsblk")
-        .arg("-l")
-        .arg("-o")
-        .arg("NAME,HOTPLUG")
-        .output()?;
-
-    let all_drives_string = String::from_utf8_lossy(&all_drives.stdout);
-    
-    let re = Regex::new(r"sd[a,b,c][1-4]").unwrap();
-    for line in all_drives_string.lines() {
-        if re.is_match(line) {
-            let drive_info = line.split(' ')
-                .filter(|d| !d.is_empty() )
-                .collect::<Vec<_>>();
-                if drive_info[1] == "1" { 
-                    
-                    //To Remove:: have the thread sleep for one second as puppy umount sometimes fails
-                    //let one_second = Duration::from_millis(1000); 
-                    //thread::sleep(one_second);
-                    let drive = match drive_info[0] {
-                        "sda1" => Usb::SDA1,
-                        "sda2" => Usb::SDA2,
-                        "sda3" => Usb::SDA3,
-                        "sda4" => Usb::SDA4,
-                        "sdb1" => Usb::SDB1,
-                        "sdb2" => Usb::SDB2,
-                        "sdb3" => Usb::SDB3,
-                        "sdb4" => Usb::SDB4,
-                        "sdc1" => Usb::SDC1,
-                        "sdc2" => Usb::SDC2,
-                        "sdc3" => Usb::SDC3,
-                        "sdc4" => Usb::SDC4,
-                        &_ => Usb::Unknown
-
-                    };
-
-                    logi!("Storage drive {} matched", &drive);
-    
-
-                // check if device mounted
-                let mut udc_info = Command::new("udisksctl")
-                    .arg("info")
-                    .arg("-b")
-                    .arg(drive.as_device_path())
-                    .stdout(Stdio::piped())
-                    .spawn()?;
-
-                let pipe = udc_info.stdout.take().unwrap();
-
-                let udc_m_grep = Command::new("grep")
-                    .arg("MountPoints")
-                    .stdin(pipe)
-                    .stdout(Stdio::piped())
-                    .spawn()?;
-
-                let udc_mounted_output = udc_m_grep.wait_with_output().expect("Failed to wait on grep");
-                let _ = udc_info.wait();
-                logi!("udisksctl and grep searched output successful");
-
-
-                let udc_mounted_output = String::from_utf8_lossy(&udc_mounted_output.stdout);
-                
-                let mount_info = udc_mounted_output.split(" ")
-                    .map(|x| x.trim())
-                    .filter(|d| !d.is_empty() )
-                    .collect::<Vec<_>>();
-
-                // if the previous step has revealed that the partition is not mounted expect a 
-                // vector of length=1 with "MountPoints" contained within.
-                if mount_info.len() == 1 { 
-                    // mount the device
-                    let udc_output = Command::new("udisksctl")
-                        .arg("mount")
-                        .arg("-b")
-                        .arg(drive.as_device_path())
-                        .output()?;
-
-                    let udc_output = String::from_utf8_lossy(&udc_output.stdout);
-
-                    let mounted_drive_info = udc_output.split(" ")
-                        .map(|x| x.trim())
-                        .filter(|d| !d.is_empty() )
-                        .collect::<Vec<_>>();
-        
-                    // this will be a vector with four parts
-                    if mounted_drive_info.len() == 4 && !mounted_drive_info[3].is_empty() {
-                        mounts.push(PathBuf::from(mounted_drive_info[3]));
-                    }
-
-                } else if mount_info.len() == 2 {
-                    mounts.push(PathBuf::from(mount_info[1]));
+    for drive in hotplug_partitions(&devices.list_hotplug()?) {
+        logi!("Storage drive {} matched", &drive);
+
+        // check if device mounted
+        let info = devices.info(drive.as_device_path())
+            .map_err(|e| e.with_device(drive.as_device_path()))?;
+        logi!("udisksctl info searched output successful");
+
+        match mount_point_from_info(&info) {
+            Some(mount_point) => {
+                logi!(MT_EVENT = "mount", MT_DEVICE = drive.as_device_path(); "Storage drive {} already mounted at {}", &drive, mount_point.display());
+                mounts.push(mount_point);
+            },
+            None if !mount => logi!("Storage drive {} is not mounted, leaving it unmounted", &drive),
+            None => {
+                // mount the device
+                let output = devices.mount(drive.as_device_path())
+                    .map_err(|e| e.with_device(drive.as_device_path()))?;
+                match mount_point_from_mount(&output) {
+                    Some(mount_point) => {
+                        logi!(MT_EVENT = "mount", MT_DEVICE = drive.as_device_path(); "Storage drive {} mounted at {}", &drive, mount_point.display());
+                        mounts.push(mount_point);
+                    },
+                    None => logw!("Storage drive {} could not be mounted", &drive)
                 }
             }
-
         }
     }
     logi!("Returning all discovered mounts");
     Ok(mounts)
 }
 
-pub fn match_uuid(uuid: &str) -> Result<PathBuf, Box<dyn Error>> {
+pub fn match_uuid(uuid: &str) -> Result<PathBuf, MediatimerError> {
+    find_uuid(&SystemDevices, uuid)
+}
+
+fn find_uuid(devices: &dyn Devices, uuid: &str) -> Result<PathBuf, MediatimerError> {
     logi!("Matching the storage device UUID");
-    let all_drives = Command::new("lsblk")
-        .arg("-l")
-        .arg("-o")
-        .arg("NAME,HOTPLUG,UUID,MOUNTPOINT")
-        .output()?;
-
-    let all_drives_string = String::from_utf8_lossy(&all_drives.stdout);
-    
-    for line in all_drives_string.lines() {
-        if line.contains(uuid) {
+    if uuid.is_empty() {
+        logw!("No storage UUID saved to match against");
+        return Err(MediatimerError::Mount(String::from("No storage device UUID saved to match against")));
+    }
+
+    match mount_point_for_uuid(&devices.list_uuids()?, uuid) {
+        Some(mount_point) => {
             logi!("UUID matched to available drive");
-            // the UUID matches, so get the path 
-            let drive_info = line.split(' ')
-                .filter(|d| !d.is_empty() )
-                .collect::<Vec<_>>();
-
-            if drive_info[2] == uuid { 
-               return Ok(PathBuf::from(drive_info[3]));
-            } else {
-                logi!("UUID does not match drive info");
+            Ok(mount_point)
+        },
+        None => {
+            logw!("UUID could not be matched to existing storage UUIDs");
+            Err(MediatimerError::Mount(format!("Could not match storage device UUID {}", uuid)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/mount/", $name))
+        };
+    }
+
+    /// Answers device queries from fixtures and records which devices were mounted
+    struct FakeDevices {
+        hotplug: &'static str,
+        info: Vec<(&'static str, &'static str)>,
+        mount: &'static str,
+        mounted: RefCell<Vec<String>>,
+    }
+
+    impl FakeDevices {
+        fn new(hotplug: &'static str, info: Vec<(&'static str, &'static str)>) -> FakeDevices {
+            FakeDevices {
+                hotplug,
+                info,
+                mount: fixture!("udisksctl_mount.txt"),
+                mounted: RefCell::new(Vec::new()),
             }
         }
     }
-    logw!("UUID could not be matched to existing storage UUIDs");
-    Err(Box::new(IoError::new(ErrorKind::Other, "Could not match UUID")))
+
+    impl Devices for FakeDevices {
+        fn list_hotplug(&self) -> Result<String, MediatimerError> {
+            Ok(self.hotplug.to_string())
+        }
+
+        fn list_uuids(&self) -> Result<String, MediatimerError> {
+            Ok(fixture!("lsblk_uuids.txt").to_string())
+        }
+
+        fn info(&self, device: &str) -> Result<String, MediatimerError> {
+            self.info.iter()
+                .find(|(d, _)| *d == device)
+                .map(|(_, info)| info.to_string())
+                .ok_or_else(|| MediatimerError::Mount(format!("Object not found for {}", device)))
+        }
+
+        fn mount(&self, device: &str) -> Result<String, MediatimerError> {
+            self.mounted.borrow_mut().push(device.to_string());
+            Ok(self.mount.to_string())
+        }
+    }
+
+    fn device_paths(partitions: Vec<Usb>) -> Vec<&'static str> {
+        partitions.iter().map(|p| p.as_device_path()).collect()
+    }
+
+    #[test]
+    fn test_hotplug_partitions() {
+        assert_eq!(device_paths(hotplug_partitions(fixture!("lsblk_hotplug_single.txt"))), vec!["/dev/sda1"]);
+        // sdc is an internal drive, so it is not hotplugged
+        assert_eq!(
+            device_paths(hotplug_partitions(fixture!("lsblk_hotplug_multiple.txt"))),
+            vec!["/dev/sda1", "/dev/sda2", "/dev/sdb1"]
+        );
+        assert!(hotplug_partitions(fixture!("lsblk_hotplug_none.txt")).is_empty());
+    }
+
+    #[test]
+    fn test_mount_point_from_info() {
+        assert_eq!(mount_point_from_info(fixture!("udisksctl_info_mounted.txt")), Some(PathBuf::from("/media/pi/KINGSTON")));
+        assert_eq!(mount_point_from_info(fixture!("udisksctl_info_unmounted.txt")), None);
+        assert_eq!(mount_point_from_info(fixture!("udisksctl_info_spaces.txt")), Some(PathBuf::from("/media/pi/MY DRIVE")));
+    }
+
+    #[test]
+    fn test_mount_point_from_mount() {
+        assert_eq!(mount_point_from_mount(fixture!("udisksctl_mount.txt")), Some(PathBuf::from("/media/pi/USB DISK")));
+        assert_eq!(mount_point_from_mount(fixture!("udisksctl_mount_legacy.txt")), Some(PathBuf::from("/media/pi/SANDISK")));
+        assert_eq!(mount_point_from_mount(fixture!("udisksctl_mount_failed.txt")), None);
+    }
+
+    #[test]
+    fn test_find_single_drive() {
+        let devices = FakeDevices::new(
+            fixture!("lsblk_hotplug_single.txt"),
+            vec![("/dev/sda1", fixture!("udisksctl_info_mounted.txt"))]
+        );
+        assert_eq!(find_mounted_drives(&devices, true).unwrap(), vec![PathBuf::from("/media/pi/KINGSTON")]);
+        assert!(devices.mounted.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_find_multiple_drives() {
+        let devices = FakeDevices::new(
+            fixture!("lsblk_hotplug_multiple.txt"),
+            vec![
+                ("/dev/sda1", fixture!("udisksctl_info_mounted.txt")),
+                ("/dev/sda2", fixture!("udisksctl_info_unmounted.txt")),
+                ("/dev/sdb1", fixture!("udisksctl_info_spaces.txt")),
+            ]
+        );
+        assert_eq!(find_mounted_drives(&devices, true).unwrap(), vec![
+            PathBuf::from("/media/pi/KINGSTON"),
+            PathBuf::from("/media/pi/USB DISK"),
+            PathBuf::from("/media/pi/MY DRIVE"),
+        ]);
+        // only the unmounted partition is mounted
+        assert_eq!(*devices.mounted.borrow(), vec!["/dev/sda2".to_string()]);
+    }
+
+    #[test]
+    fn test_find_drives_without_mounting() {
+        let devices = FakeDevices::new(
+            fixture!("lsblk_hotplug_multiple.txt"),
+            vec![
+                ("/dev/sda1", fixture!("udisksctl_info_mounted.txt")),
+                ("/dev/sda2", fixture!("udisksctl_info_unmounted.txt")),
+                ("/dev/sdb1", fixture!("udisksctl_info_spaces.txt")),
+            ]
+        );
+        assert_eq!(find_mounted_drives(&devices, false).unwrap(), vec![
+            PathBuf::from("/media/pi/KINGSTON"),
+            PathBuf::from("/media/pi/MY DRIVE"),
+        ]);
+        assert!(devices.mounted.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_failed_mount_is_skipped() {
+        let mut devices = FakeDevices::new(
+            fixture!("lsblk_hotplug_single.txt"),
+            vec![("/dev/sda1", fixture!("udisksctl_info_unmounted.txt"))]
+        );
+        devices.mount = fixture!("udisksctl_mount_failed.txt");
+        assert!(find_mounted_drives(&devices, true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_no_drives() {
+        let devices = FakeDevices::new(fixture!("lsblk_hotplug_none.txt"), Vec::new());
+        assert!(find_mounted_drives(&devices, true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_find_uuid() {
+        let devices = FakeDevices::new(fixture!("lsblk_hotplug_none.txt"), Vec::new());
+        assert_eq!(find_uuid(&devices, "5E3A-91C2").unwrap(), PathBuf::from("/media/pi/KINGSTON"));
+        assert_eq!(find_uuid(&devices, "0C4D-7F18").unwrap(), PathBuf::from("/media/pi/MY DRIVE"));
+        // lsblk escapes quotes and spaces
+        assert_eq!(find_uuid(&devices, "7A0B-33DE").unwrap(), PathBuf::from("/media/pi/Holiday\"s Films"));
+        // the partition exists but is not mounted
+        assert!(find_uuid(&devices, "b1f0c6f2-3e1d-4a8b-9d57-2c6f0e6e8a11").is_err());
+        // an empty UUID must not match the whole disks, which have no UUID
+        assert!(find_uuid(&devices, "").is_err());
+        assert!(find_uuid(&devices, "FFFF-0000").is_err());
+    }
+
+    #[test]
+    fn test_drive_summary() {
+        assert_eq!(drive_summary(fixture!("lsblk_uuids.txt")), vec![
+            "sda1 (removable) UUID 5E3A-91C2 mounted at /media/pi/KINGSTON",
+            "sdb1 (removable) UUID 0C4D-7F18 mounted at /media/pi/MY DRIVE",
+            "sdc1 (removable) UUID b1f0c6f2-3e1d-4a8b-9d57-2c6f0e6e8a11 not mounted",
+            "sdd1 (removable) UUID 7A0B-33DE mounted at /media/pi/Holiday\"s Films",
+            "mmcblk0p1 UUID 4AD7-B4D5 mounted at /boot/firmware",
+            "mmcblk0p2 UUID 2e5a9b6e-93cd-4a6a-8a3b-0a1c7a3c2b1e mounted at /",
+        ]);
+    }
+
+    #[test]
+    fn test_parse_pairs() {
+        let pairs = parse_pairs(r#"NAME="sdb1" HOTPLUG="1" UUID="" MOUNTPOINT="/media/pi/A\x20B""#);
+        assert_eq!(pairs, vec![
+            ("NAME".to_string(), "sdb1".to_string()),
+            ("HOTPLUG".to_string(), "1".to_string()),
+            ("UUID".to_string(), String::new()),
+            ("MOUNTPOINT".to_string(), "/media/pi/A B".to_string()),
+        ]);
+    }
 }
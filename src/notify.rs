@@ -0,0 +1,165 @@
+use std::{
+    sync::{
+        Arc,
+        LazyLock,
+        Mutex
+    },
+    thread,
+    time::{
+        Duration,
+        Instant
+    },
+};
+
+use sd_notify::NotifyState;
+
+use crate::{
+    logi,
+    logw
+};
+use log::{
+    info,
+    warn
+};
+
+use crate::{
+    Task,
+    playback::Playback,
+    status
+};
+
+/// The longest the main loop may go without reporting before it is treated as hung. Anything
+/// that waits for a long time reports at least this often; the scheduler's clock wakes every
+/// 30 seconds.
+const HEARTBEAT_STALE: Duration = Duration::from_secs(120);
+
+/// How often an idle main loop reports that it is alive
+const IDLE_REPORT: Duration = Duration::from_secs(30);
+
+/// When the main loop last reported that it is alive
+static HEARTBEAT: LazyLock<Mutex<Instant>> = LazyLock::new(|| Mutex::new(Instant::now()));
+
+/// Marks the main loop as alive
+pub fn alive() {
+    *HEARTBEAT.lock().unwrap() = Instant::now();
+}
+
+/// Waits forever with nothing to do, still reporting that the main loop is alive
+pub fn idle() -> ! {
+    loop {
+        alive();
+        thread::park_timeout(IDLE_REPORT);
+    }
+}
+
+fn is_fresh(last: Instant, now: Instant) -> bool {
+    now.saturating_duration_since(last) <= HEARTBEAT_STALE
+}
+
+/// Sends the states to systemd. Nothing is sent when not run by systemd.
+fn send(states: &[NotifyState]) {
+    if let Err(e) = sd_notify::notify(false, states) {
+        logw!("Failed to notify systemd: {}", e);
+    }
+}
+
+/// Tells systemd that start up has finished
+pub fn ready() {
+    send(&[NotifyState::Ready]);
+}
+
+/// Tells systemd that the service is shutting down
+pub fn stopping() {
+    send(&[NotifyState::Stopping]);
+}
+
+/// Sets the status shown by `systemctl status`
+pub fn status(status: &str) {
+    send(&[NotifyState::Status(status)]);
+}
+
+/// Adds the systemd scope the player runs in, if it has one
+fn in_scope(status: String, scope: Option<&str>) -> String {
+    match scope {
+        Some(scope) => format!("{} in {}.scope", status, scope),
+        None => status
+    }
+}
+
+fn playing_status(task: &Task, scope: Option<&str>) -> String {
+    in_scope(format!("Playing {} {}", task.proc_type.as_str(), task.source()), scope)
+}
+
+/// Shows the task now playing in `systemctl status`, and writes its scope to the status file
+pub fn playing(task: &Task, scope: Option<&str>) {
+    status(&playing_status(task, scope));
+    status::write_scope(scope);
+}
+
+/// Shows the interrupt now playing in `systemctl status`, and writes its scope to the status file
+pub fn playing_interrupt(name: &str, scope: Option<&str>) {
+    status(&in_scope(format!("Playing interrupt {}", name), scope));
+    status::write_scope(scope);
+}
+
+/// Pings the systemd watchdog at half the interval it asks for, as long as the main loop is
+/// alive and the playback controller can take a new request within a quarter of the
+/// watchdog interval. If either hangs the pings stop and systemd restarts the service. Does nothing if
+/// the unit has no `WatchdogSec`.
+pub fn start_watchdog(playback: Arc<Playback>) {
+    let mut usec = 0;
+    if !sd_notify::watchdog_enabled(false, &mut usec) {
+        return;
+    }
+    let interval = Duration::from_micros(usec) / 2;
+    logi!("Pinging the systemd watchdog every {}s", interval.as_secs());
+    thread::spawn(move || {
+        let mut reported = false;
+        loop {
+            thread::sleep(interval);
+            // a deadlocked playback controller stops the pings
+            if !playback.is_responsive(interval / 2) {
+                if !reported {
+                    logw!("Playback controller is stuck while {}, leaving the watchdog to restart the service", playback.state());
+                    reported = true;
+                }
+            } else if is_fresh(*HEARTBEAT.lock().unwrap(), Instant::now()) {
+                send(&[NotifyState::Watchdog]);
+                reported = false;
+            } else if !reported {
+                logw!("Main loop has not reported for {}s, leaving the watchdog to restart the service", HEARTBEAT_STALE.as_secs());
+                reported = true;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Autoloop,
+        Model,
+        ProcType
+    };
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_heartbeat() {
+        let last = Instant::now();
+        assert!(is_fresh(last, last));
+        assert!(is_fresh(last, last + HEARTBEAT_STALE));
+        assert!(!is_fresh(last, last + HEARTBEAT_STALE + Duration::from_secs(1)));
+        // a heartbeat newer than the check is fresh
+        assert!(is_fresh(last + Duration::from_secs(5), last));
+    }
+
+    #[test]
+    fn test_playing_status() {
+        let task = Task::new(Model::Pro, ProcType::Video, Autoloop::Yes, PathBuf::from("/media/pi/KINGSTON/advert.mp4"), 5, String::new());
+        assert_eq!(playing_status(&task, None), "Playing video /media/pi/KINGSTON/advert.mp4");
+        let web = Task::new(Model::Pro, ProcType::Web, Autoloop::No, PathBuf::new(), 5, String::from("https://example.com"));
+        assert_eq!(playing_status(&web, None), "Playing web https://example.com");
+        assert_eq!(playing_status(&web, Some("mediatimer-web-812-3")), "Playing web https://example.com in mediatimer-web-812-3.scope");
+    }
+}
@@ -0,0 +1,378 @@
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        Mutex,
+        TryLockError
+    },
+    thread,
+    time::{
+        Duration,
+        Instant
+    },
+};
+
+use crate::{
+    logi,
+    logd
+};
+use log::{
+    info,
+    debug
+};
+
+use crate::{
+    RunningTask,
+    Task,
+    background,
+    error::{
+        MediatimerError,
+        display_error_card
+    },
+    launcher::Launcher,
+    proof::Play,
+    shutdown::{
+        self,
+        Stopped,
+        TERM_TIMEOUT
+    },
+    task_runner::spawn_child
+};
+
+/// How often the request lock is tried while checking that the controller is responsive
+const RESPONSIVE_POLL: Duration = Duration::from_millis(100);
+
+/// What the screen is showing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    /// Nothing is running
+    Idle,
+    /// Only the background is showing
+    Background,
+    /// A player is being launched, whatever was showing before is still running
+    Starting,
+    /// A task or interrupt is playing
+    Playing,
+    /// Moving between players, both may be running while the old one is stopped
+    Transitioning,
+    /// The error card is showing until start up is tried again
+    Error,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = match self {
+            State::Idle => "idle",
+            State::Background => "background",
+            State::Starting => "starting",
+            State::Playing => "playing",
+            State::Transitioning => "transitioning",
+            State::Error => "error"
+        };
+        write!(f, "{}", state)
+    }
+}
+
+/// A player that has been started
+#[derive(Debug, Clone, PartialEq)]
+pub struct Started {
+    pub pid: u32,
+    /// The systemd scope it runs in, when `MT_SCOPE` is set
+    pub scope: Option<String>,
+}
+
+#[derive(Debug)]
+struct Players {
+    state: State,
+    /// The background, or the error card in the `Error` state
+    background: Option<RunningTask>,
+    /// The task or interrupt playing
+    player: Option<RunningTask>,
+}
+
+impl Players {
+    /// The state that matches what is left running once a request has finished
+    fn settled(&self) -> State {
+        match (&self.player, &self.background) {
+            (Some(_), _) => State::Playing,
+            (None, Some(_)) => State::Background,
+            (None, None) => State::Idle
+        }
+    }
+
+    fn take_all(&mut self) -> Vec<RunningTask> {
+        self.player.take().into_iter().chain(self.background.take()).collect()
+    }
+}
+
+/// Owns every player and applies start and stop requests one at a time, in the order they
+/// are made. The scheduler, start up catch-up, interrupts and shutdown all go through this, so
+/// a request can never stop a player that a later request started.
+pub struct Playback {
+    launcher: Arc<dyn Launcher>,
+    /// Held for the whole of each request
+    requests: Mutex<()>,
+    /// Only held briefly, so the state can be read while a request is in progress
+    players: Mutex<Players>,
+}
+
+impl Playback {
+    pub fn new(launcher: Arc<dyn Launcher>) -> Playback {
+        Playback {
+            launcher,
+            requests: Mutex::new(()),
+            players: Mutex::new(Players { state: State::Idle, background: None, player: None }),
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.players.lock().unwrap().state
+    }
+
+    /// True if a new request could be started within `wait`. A request that is stopping
+    /// players holds the lock for a few seconds, so only a request stuck for longer, or one
+    /// that panicked, counts as unresponsive.
+    pub fn is_responsive(&self, wait: Duration) -> bool {
+        let deadline = Instant::now() + wait;
+        loop {
+            match self.requests.try_lock() {
+                Ok(_) => return true,
+                Err(TryLockError::Poisoned(_)) => return false,
+                Err(TryLockError::WouldBlock) if Instant::now() >= deadline => return false,
+                Err(TryLockError::WouldBlock) => thread::sleep(RESPONSIVE_POLL)
+            }
+        }
+    }
+
+    fn set_state(&self, state: State) {
+        let mut players = self.players.lock().unwrap();
+        if players.state != state {
+            logd!("Playback {} -> {}", players.state, state);
+            players.state = state;
+        }
+    }
+
+    fn settle(&self) {
+        let state = self.players.lock().unwrap().settled();
+        self.set_state(state);
+    }
+
+    /// Starts the background if it is not already running
+    fn start_background(&self) -> Result<(), MediatimerError> {
+        if self.players.lock().unwrap().background.is_some() {
+            return Ok(());
+        }
+        let background = background::run(self.launcher.as_ref())?;
+        self.players.lock().unwrap().background = Some(background);
+        Ok(())
+    }
+
+    /// Shows the background, so the screen is never left empty. Anything playing is left
+    /// playing.
+    pub fn show_background(&self) -> Result<(), MediatimerError> {
+        let _request = self.requests.lock().unwrap();
+        let shown = self.start_background();
+        self.settle();
+        shown
+    }
+
+    /// Launches the task, then stops whatever was showing before so the two overlap. If the
+    /// task cannot be launched, whatever was showing is left running.
+    pub fn play(&self, task: &Task, seek_seconds: &str) -> Result<Started, MediatimerError> {
+        let _request = self.requests.lock().unwrap();
+        self.set_state(State::Starting);
+        let running = match spawn_child(self.launcher.as_ref(), task, seek_seconds) {
+            Ok(running) => running.with_play(Play::begin(task)),
+            Err(e) => {
+                self.settle();
+                return Err(MediatimerError::Spawn(format!("Failed to launch {} {}: {}", task.proc_type.as_str(), task.source(), e)));
+            }
+        };
+        let started = Started { pid: running.child.id(), scope: running.scope.clone() };
+
+        let replaced = {
+            let mut players = self.players.lock().unwrap();
+            let previous = players.player.replace(running);
+            previous.into_iter().chain(players.background.take()).collect::<Vec<RunningTask>>()
+        };
+        if !replaced.is_empty() {
+            self.set_state(State::Transitioning);
+            shutdown::stop_tasks(replaced, TERM_TIMEOUT);
+        }
+        self.set_state(State::Playing);
+        Ok(started)
+    }
+
+    /// Stops the task or interrupt playing and shows the background in its place. The
+    /// background is started first so the two overlap.
+    pub fn stop(&self) -> Result<(), MediatimerError> {
+        let _request = self.requests.lock().unwrap();
+        let Some(player) = self.players.lock().unwrap().player.take() else {
+            logi!("Nothing playing to stop");
+            let shown = self.start_background();
+            self.settle();
+            return shown;
+        };
+        self.set_state(State::Transitioning);
+        let shown = self.start_background();
+        shutdown::stop_tasks(vec![player], TERM_TIMEOUT);
+        self.settle();
+        shown
+    }
+
+    /// True once the player started with `pid` has exited, or has been replaced
+    pub fn has_exited(&self, pid: u32) -> bool {
+        self.players.lock().unwrap()
+            .player
+            .as_mut()
+            .filter(|player| player.child.id() == pid)
+            .is_none_or(|player| matches!(player.child.try_wait(), Ok(Some(_))))
+    }
+
+    /// Waits for the player to exit by itself
+    #[cfg(test)]
+    pub fn wait(&self) -> Option<std::process::ExitStatus> {
+        self.players.lock().unwrap().player.as_mut().and_then(|player| player.child.wait().ok())
+    }
+
+    /// Stops everything and shows the error card in its place
+    pub fn show_error(&self, error: &MediatimerError) -> Result<(), MediatimerError> {
+        let _request = self.requests.lock().unwrap();
+        let running = self.players.lock().unwrap().take_all();
+        shutdown::stop_tasks(running, TERM_TIMEOUT);
+        match display_error_card(self.launcher.as_ref(), error) {
+            Ok(card) => {
+                self.players.lock().unwrap().background = Some(card);
+                self.set_state(State::Error);
+                Ok(())
+            },
+            Err(e) => {
+                self.settle();
+                Err(e)
+            }
+        }
+    }
+
+    /// Stops everything, including the background
+    pub fn stop_all(&self) -> Stopped {
+        self.stop_all_then(|stopped| stopped)
+    }
+
+    /// Stops everything and then runs `then` before any other request can start a player
+    pub fn stop_all_then<R>(&self, then: impl FnOnce(Stopped) -> R) -> R {
+        let _request = self.requests.lock().unwrap();
+        let running = self.players.lock().unwrap().take_all();
+        let stopped = shutdown::stop_tasks(running, TERM_TIMEOUT);
+        self.settle();
+        then(stopped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Autoloop,
+        Model,
+        ProcType,
+        launcher::mock::{
+            MockLauncher,
+            Outcome
+        }
+    };
+    use std::path::PathBuf;
+
+    fn video() -> Task {
+        Task::new(Model::Pro, ProcType::Video, Autoloop::Yes, PathBuf::from("/media/film.mp4"), 5, String::new())
+    }
+
+    fn programs(launcher: &MockLauncher) -> Vec<String> {
+        launcher.launched().into_iter().map(|line| line[0].clone()).collect()
+    }
+
+    #[test]
+    fn test_play_and_stop() {
+        let launcher = Arc::new(MockLauncher::new(Outcome::Run));
+        let playback = Playback::new(launcher.clone());
+        assert_eq!(playback.state(), State::Idle);
+
+        playback.show_background().unwrap();
+        assert_eq!(playback.state(), State::Background);
+
+        let first = playback.play(&video(), "0").unwrap();
+        assert_eq!(playback.state(), State::Playing);
+        assert!(playback.players.lock().unwrap().background.is_none());
+        assert!(!playback.has_exited(first.pid));
+
+        // a new task replaces the one playing
+        let second = playback.play(&video(), "0").unwrap();
+        assert!(playback.has_exited(first.pid));
+        assert!(!playback.has_exited(second.pid));
+
+        playback.stop().unwrap();
+        assert_eq!(playback.state(), State::Background);
+        assert!(playback.has_exited(second.pid));
+        assert_eq!(programs(&launcher), vec!["ffplay", "ffplay", "ffplay", "ffplay"]);
+
+        // stopping again leaves the background as it is
+        playback.stop().unwrap();
+        assert_eq!(launcher.launched().len(), 4);
+
+        assert_eq!(playback.stop_all(), Stopped { terminated: 1, killed: 0 });
+        assert_eq!(playback.state(), State::Idle);
+    }
+
+    #[test]
+    fn test_player_exits() {
+        let launcher = Arc::new(MockLauncher::new(Outcome::Exit(0)));
+        let playback = Playback::new(launcher);
+        let started = playback.play(&video(), "0").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !playback.has_exited(started.pid) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert!(playback.has_exited(started.pid));
+        // still playing as far as the schedule is concerned, until it is stopped
+        assert_eq!(playback.state(), State::Playing);
+        playback.stop_all();
+    }
+
+    #[test]
+    fn test_is_responsive() {
+        let playback = Playback::new(Arc::new(MockLauncher::new(Outcome::Run)));
+        assert!(playback.is_responsive(Duration::ZERO));
+
+        // a request stuck holding the lock
+        let stuck = playback.requests.lock().unwrap();
+        assert!(!playback.is_responsive(Duration::from_millis(250)));
+        drop(stuck);
+        assert!(playback.is_responsive(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_requests_are_serialised() {
+        let launcher = Arc::new(MockLauncher::new(Outcome::Run));
+        let playback = Arc::new(Playback::new(launcher.clone()));
+        let handles = (0..4).map(|i| {
+            let playback = Arc::clone(&playback);
+            thread::spawn(move || if i % 2 == 0 {
+                playback.play(&video(), "0").map(|_| ())
+            } else {
+                playback.stop()
+            })
+        }).collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap().unwrap();
+        }
+
+        // exactly one player or the background is left, whatever order the requests ran in
+        let mut players = playback.players.lock().unwrap();
+        let left = players.player.is_some() as usize + players.background.is_some() as usize;
+        assert_eq!(left, 1);
+        assert_eq!(players.state, players.settled());
+        let running = players.take_all();
+        drop(players);
+        shutdown::stop_tasks(running, TERM_TIMEOUT);
+    }
+}
@@ -0,0 +1,175 @@
+use std::io;
+
+use chrono::TimeDelta;
+use serde_json::{
+    Value,
+    json
+};
+
+use crate::{
+    AdvancedSchedule,
+    Autoloop,
+    Config,
+    Task,
+    build_timetable,
+    error::MediatimerError,
+    interrupt::Interrupt,
+    scheduler::{
+        Action,
+        ScheduledEvent
+    },
+    timezone
+};
+
+/// The number of days previewed when `--preview` is given without a number
+const DEFAULT_DAYS: i64 = 7;
+
+/// Options for `--preview [days] [--json]`, which prints what the device will do instead of
+/// running the schedule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Options {
+    pub days: i64,
+    pub json: bool,
+}
+
+impl Options {
+    /// Reads the preview options from the command line arguments. Returns None when
+    /// `--preview` was not given.
+    pub fn from_args<I: Iterator<Item = String>>(args: I) -> Result<Option<Options>, MediatimerError> {
+        let mut preview = false;
+        let mut days = DEFAULT_DAYS;
+        let mut json = false;
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--preview" => {
+                    preview = true;
+                    if let Some(value) = args.next_if(|a| !a.starts_with("--")) {
+                        days = value.parse::<i64>()
+                            .ok()
+                            .filter(|d| *d > 0)
+                            .ok_or_else(|| MediatimerError::Config(format!("Preview days must be a positive number: {}", value)))?;
+                    }
+                },
+                "--json" => json = true,
+                _ => return Err(MediatimerError::Config(format!("Argument not recognised: {}", arg)))
+            }
+        }
+        if json && !preview {
+            return Err(MediatimerError::Config(String::from("--json can only be used with --preview")));
+        }
+        Ok(preview.then_some(Options { days, json }))
+    }
+}
+
+fn describe(task: &Task) -> String {
+    let looped = if matches!(task.auto_loop, Autoloop::Yes) { ", looped" } else { "" };
+    format!("{} {}{}", task.proc_type.as_str(), task.source(), looped)
+}
+
+fn task_json(task: &Task) -> Value {
+    json!({
+        "proc_type": task.proc_type.as_str(),
+        "source": task.source(),
+        "autoloop": matches!(task.auto_loop, Autoloop::Yes),
+    })
+}
+
+/// What an event does, and the task it runs if it starts one
+fn resolve(event: &ScheduledEvent, config: &Config, interrupts: &[Interrupt]) -> (String, Option<Task>) {
+    match event.action {
+        Action::Start => (String::from("start"), Some(config.task.clone())),
+        Action::Stop => (String::from("stop"), None),
+        Action::Interrupt(index) => (
+            format!("interrupt {}", interrupts[index].name),
+            Some(interrupts[index].task.lock().unwrap().clone())
+        ),
+        Action::InterruptEnd(index) => (format!("end interrupt {}", interrupts[index].name), None)
+    }
+}
+
+/// Prints the resolved timeline for the next `options.days` days, either as text or as JSON
+pub fn print(config: &Config, options: Options) -> Result<(), MediatimerError> {
+    let from = timezone::now();
+    let until = from + TimeDelta::days(options.days);
+
+    if config.schedule == AdvancedSchedule::No {
+        if options.json {
+            let preview = json!({
+                "timezone": timezone::get().name(),
+                "from": from.to_rfc3339(),
+                "until": until.to_rfc3339(),
+                "scheduled": false,
+                "task": task_json(&config.task),
+                "events": [],
+            });
+            println!("{}", serde_json::to_string_pretty(&preview).map_err(io::Error::other)?);
+        } else {
+            println!("No schedule is set, the task runs continuously: {}", describe(&config.task));
+        }
+        return Ok(());
+    }
+
+    let (timetable, interrupts) = build_timetable(config)?;
+    let events = timetable.timeline(from, until);
+    let active = timetable.active_window(from);
+
+    if options.json {
+        let events = events.iter().map(|event| {
+            let (action, task) = resolve(event, config, &interrupts);
+            json!({
+                "at": event.at.to_rfc3339(),
+                "action": action,
+                "window_start": event.window_start.to_rfc3339(),
+                "season": timetable.season_on(event.at.date_naive()),
+                "task": task.as_ref().map(task_json),
+            })
+        }).collect::<Vec<Value>>();
+        let preview = json!({
+            "timezone": timezone::get().name(),
+            "from": from.to_rfc3339(),
+            "until": until.to_rfc3339(),
+            "scheduled": true,
+            "active_window": active.map(|a| a.to_rfc3339()),
+            "task": task_json(&config.task),
+            "events": events,
+        });
+        println!("{}", serde_json::to_string_pretty(&preview).map_err(io::Error::other)?);
+        return Ok(());
+    }
+
+    println!("Schedule preview for {} days from {} ({})", options.days, from.format("%a %Y-%m-%d %H:%M:%S %z"), timezone::get().name());
+    match active {
+        Some(window_start) => println!("A window is active now, it started at {}", window_start.format("%a %Y-%m-%d %H:%M:%S %z")),
+        None => println!("No window is active now, the background is showing")
+    }
+    if events.is_empty() {
+        println!("No events are scheduled in this period");
+    }
+    for event in events.iter() {
+        let (action, task) = resolve(event, config, &interrupts);
+        let task = task.as_ref().map(describe).unwrap_or_default();
+        println!("{}  {:<24} {}", event.at.format("%a %Y-%m-%d %H:%M:%S %z"), action, task);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> impl Iterator<Item = String> {
+        values.iter().map(|v| v.to_string()).collect::<Vec<String>>().into_iter()
+    }
+
+    #[test]
+    fn test_from_args() {
+        assert_eq!(Options::from_args(args(&[])).unwrap(), None);
+        assert_eq!(Options::from_args(args(&["--preview"])).unwrap(), Some(Options { days: 7, json: false }));
+        assert_eq!(Options::from_args(args(&["--preview", "14", "--json"])).unwrap(), Some(Options { days: 14, json: true }));
+        assert_eq!(Options::from_args(args(&["--json", "--preview"])).unwrap(), Some(Options { days: 7, json: true }));
+        assert!(Options::from_args(args(&["--preview", "0"])).is_err());
+        assert!(Options::from_args(args(&["--json"])).is_err());
+        assert!(Options::from_args(args(&["--unknown"])).is_err());
+    }
+}
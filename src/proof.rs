@@ -0,0 +1,441 @@
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap
+    },
+    fs::{
+        self,
+        File,
+        OpenOptions
+    },
+    io::{
+        self,
+        Read,
+        Write
+    },
+    os::unix::process::ExitStatusExt,
+    path::{
+        Path,
+        PathBuf
+    },
+    process::ExitStatus,
+    sync::{
+        Arc,
+        LazyLock,
+        Mutex,
+        OnceLock
+    },
+    thread::{
+        self,
+        JoinHandle
+    },
+    time::SystemTime,
+};
+
+use chrono::{
+    DateTime,
+    NaiveDate,
+    TimeDelta
+};
+use chrono_tz::Tz;
+use serde_json::{
+    Value,
+    json
+};
+use nix::{
+    sys::wait::{
+        self,
+        Id,
+        WaitPidFlag
+    },
+    unistd::Pid
+};
+use sha2::{
+    Digest,
+    Sha256
+};
+
+use crate::logw;
+use log::warn;
+
+use crate::{
+    ProcType,
+    Task,
+    error::MediatimerError,
+    timezone
+};
+
+/// The number of days summarised when `--proof-of-play` is given without dates
+const DEFAULT_DAYS: i64 = 7;
+
+/// A file as it was when hashed: its path, size and modified time
+type FileVersion = (PathBuf, u64, SystemTime);
+
+/// File hashes already worked out, keyed by the file version so that a changed file is hashed
+/// again. Media files can be large, so each is only read once.
+static HASHES: LazyLock<Mutex<HashMap<FileVersion, String>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Records still being written, joined by `flush` before the service exits
+static PENDING: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+
+fn proof_path() -> PathBuf {
+    let username = whoami::username();
+    ["/home/", &username, ".mediatimer_config/proof_of_play.jsonl"].iter().collect()
+}
+
+/// What a task was playing, taken when it starts
+#[derive(Debug, Clone)]
+pub struct Play {
+    task: String,
+    proc_type: ProcType,
+    file: Option<PathBuf>,
+    start: DateTime<Tz>,
+    /// When the player exited by itself, if it did
+    ended: Arc<OnceLock<DateTime<Tz>>>,
+}
+
+impl Play {
+    pub fn begin(task: &Task) -> Play {
+        // slideshows play a directory and web pages have no file to hash
+        let file = match task.proc_type {
+            ProcType::Web | ProcType::Browser | ProcType::Slideshow => None,
+            _ => Some(task.file.clone())
+        };
+        Play {
+            task: task.source(),
+            proc_type: task.proc_type,
+            file,
+            start: timezone::now(),
+            ended: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// The task being played, as it is named in the logs
+    pub fn task(&self) -> &str {
+        &self.task
+    }
+
+    /// Notes the time the player with `pid` exits, so a player that finishes by itself is
+    /// recorded as ending then rather than when it is next stopped. The exit is only watched
+    /// for, the player is still reaped by its owner.
+    pub fn watch(&self, pid: u32) {
+        let ended = Arc::clone(&self.ended);
+        thread::spawn(move || {
+            // fails once the player has been reaped, when it was stopped rather than exiting
+            if wait::waitid(Id::Pid(Pid::from_raw(pid as i32)), WaitPidFlag::WEXITED | WaitPidFlag::WNOWAIT).is_ok() {
+                let _ = ended.set(timezone::now());
+            }
+        });
+    }
+}
+
+/// Why a task stopped playing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EndReason {
+    /// Stopped by the schedule or pre-empted by an interrupt
+    Stopped,
+    /// The player finished by itself with the exit code
+    Exited(i32),
+    /// The player was killed by the signal
+    Crashed(i32),
+}
+
+impl EndReason {
+    pub fn from_status(status: ExitStatus) -> EndReason {
+        match (status.code(), status.signal()) {
+            (Some(code), _) => EndReason::Exited(code),
+            (None, Some(signal)) => EndReason::Crashed(signal),
+            (None, None) => EndReason::Stopped
+        }
+    }
+
+    fn as_string(&self) -> String {
+        match self {
+            EndReason::Stopped => String::from("stopped"),
+            EndReason::Exited(code) => format!("exited {}", code),
+            EndReason::Crashed(signal) => format!("crashed signal {}", signal)
+        }
+    }
+}
+
+fn hash_file(path: &Path) -> io::Result<String> {
+    let metadata = fs::metadata(path)?;
+    let key = (path.to_path_buf(), metadata.len(), metadata.modified()?);
+    if let Some(hash) = HASHES.lock().unwrap().get(&key) {
+        return Ok(hash.clone());
+    }
+
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    let hash = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    HASHES.lock().unwrap().insert(key, hash.clone());
+    Ok(hash)
+}
+
+/// One JSON line of the proof of play log
+fn record_line(play: &Play, file_hash: Option<String>, end: DateTime<Tz>, reason: EndReason) -> String {
+    json!({
+        "task": play.task,
+        "proc_type": play.proc_type.as_str(),
+        "file_hash": file_hash.map(|h| format!("sha256:{}", h)),
+        "start": play.start.to_rfc3339(),
+        "end": end.to_rfc3339(),
+        "duration_secs": (end - play.start).num_seconds().max(0),
+        "exit_reason": reason.as_string(),
+    }).to_string()
+}
+
+fn append(path: &Path, line: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    // a single write keeps each record whole if two are written at once
+    file.write_all(format!("{}\n", line).as_bytes())
+}
+
+/// Appends a record of the play to the proof of play log. The file is hashed on another thread
+/// so that stopping a task is never held up by reading a large file; `flush` waits for it.
+pub fn record(play: Play, reason: EndReason) {
+    let end = play.ended.get().copied().unwrap_or_else(timezone::now);
+    let writing = thread::spawn(move || {
+        let file_hash = play.file.as_deref().and_then(|file| match hash_file(file) {
+            Ok(hash) => Some(hash),
+            Err(e) => {
+                logw!("Failed to hash {} for proof of play: {}", file.display(), e);
+                None
+            }
+        });
+        if let Err(e) = append(&proof_path(), &record_line(&play, file_hash, end, reason)) {
+            logw!("Failed to write proof of play record: {}", e);
+        }
+    });
+    let mut pending = PENDING.lock().unwrap();
+    pending.retain(|handle| !handle.is_finished());
+    pending.push(writing);
+}
+
+/// Waits for every record to be written, so none are lost when the service exits
+pub fn flush() {
+    let pending = PENDING.lock().unwrap().drain(..).collect::<Vec<JoinHandle<()>>>();
+    for handle in pending {
+        let _ = handle.join();
+    }
+}
+
+/// Options for `--proof-of-play [from] [to]`, which summarises what played between the two
+/// dates, inclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Options {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl Options {
+    /// Reads the summary options from the command line arguments. Returns None when
+    /// `--proof-of-play` was not given. `today` is only asked for when it is.
+    pub fn from_args<I: Iterator<Item = String>>(args: I, today: impl FnOnce() -> NaiveDate) -> Result<Option<Options>, MediatimerError> {
+        let args = args.collect::<Vec<String>>();
+        if args.first().is_none_or(|a| a != "--proof-of-play") {
+            return Ok(None);
+        }
+        let dates = args[1..].iter()
+            .map(|a| NaiveDate::parse_from_str(a, "%Y-%m-%d")
+                .map_err(|_| MediatimerError::Config(format!("Proof of play dates must be written as YYYY-MM-DD: {}", a))))
+            .collect::<Result<Vec<NaiveDate>, MediatimerError>>()?;
+        let (from, to) = match dates.as_slice() {
+            [] => {
+                let today = today();
+                (today - TimeDelta::days(DEFAULT_DAYS - 1), today)
+            },
+            [from] => (*from, today()),
+            [from, to] => (*from, *to),
+            _ => return Err(MediatimerError::Config(String::from("--proof-of-play takes at most two dates")))
+        };
+        if from > to {
+            return Err(MediatimerError::Config(format!("Proof of play start date {} is after the end date {}", from, to)));
+        }
+        Ok(Some(Options { from, to }))
+    }
+}
+
+/// The plays of one task in the summary
+#[derive(Debug, Default, PartialEq)]
+struct Total {
+    proc_type: String,
+    file_hash: Option<String>,
+    plays: u32,
+    duration_secs: i64,
+    reasons: BTreeMap<String, u32>,
+}
+
+/// Totals the records that started between the dates, by task. Lines that cannot be read are
+/// counted rather than stopping the summary.
+fn summarise(log: &str, options: Options) -> (BTreeMap<String, Total>, usize) {
+    let mut totals: BTreeMap<String, Total> = BTreeMap::new();
+    let mut unreadable = 0;
+    for line in log.lines().filter(|l| !l.trim().is_empty()) {
+        let Ok(record) = serde_json::from_str::<Value>(line) else {
+            unreadable += 1;
+            continue;
+        };
+        let Some(start) = record["start"].as_str().and_then(|s| DateTime::parse_from_rfc3339(s).ok()) else {
+            unreadable += 1;
+            continue;
+        };
+        let date = start.date_naive();
+        if date < options.from || date > options.to {
+            continue;
+        }
+        let total = totals.entry(record["task"].as_str().unwrap_or_default().to_string()).or_default();
+        total.proc_type = record["proc_type"].as_str().unwrap_or_default().to_string();
+        if let Some(hash) = record["file_hash"].as_str() {
+            total.file_hash = Some(hash.to_string());
+        }
+        total.plays += 1;
+        total.duration_secs += record["duration_secs"].as_i64().unwrap_or(0);
+        *total.reasons.entry(record["exit_reason"].as_str().unwrap_or("unknown").to_string()).or_default() += 1;
+    }
+    (totals, unreadable)
+}
+
+fn format_duration(secs: i64) -> String {
+    format!("{}h {:02}m {:02}s", secs / 3600, (secs % 3600) / 60, secs % 60)
+}
+
+/// Prints the number of plays and time played of each task between the dates
+pub fn print_summary(options: Options) -> Result<(), MediatimerError> {
+    let path = proof_path();
+    let log = match fs::read_to_string(&path) {
+        Ok(log) => log,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(MediatimerError::Io(e).with_path(&path))
+    };
+    let (totals, unreadable) = summarise(&log, options);
+
+    println!("Proof of play from {} to {}", options.from, options.to);
+    if totals.is_empty() {
+        println!("Nothing played in this period");
+    }
+    for (task, total) in totals.iter() {
+        match &total.file_hash {
+            Some(hash) => println!("{} ({}, {})", task, total.proc_type, hash),
+            None => println!("{} ({})", task, total.proc_type)
+        }
+        let reasons = total.reasons.iter().map(|(reason, count)| format!("{} {}", count, reason)).collect::<Vec<String>>();
+        println!("  {} plays, {} played, {}", total.plays, format_duration(total.duration_secs), reasons.join(", "));
+    }
+    if unreadable > 0 {
+        println!("{} records in {} could not be read", unreadable, path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Autoloop,
+        Model
+    };
+    use chrono::TimeZone;
+    use chrono_tz::Tz::Europe__London as London;
+    use std::{
+        process::Command,
+        time::{
+            Duration,
+            Instant
+        }
+    };
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn args(values: &[&str]) -> impl Iterator<Item = String> {
+        values.iter().map(|v| v.to_string()).collect::<Vec<String>>().into_iter()
+    }
+
+    #[test]
+    fn test_end_reason() {
+        assert_eq!(EndReason::from_status(ExitStatus::from_raw(0)), EndReason::Exited(0));
+        assert_eq!(EndReason::from_status(ExitStatus::from_raw(1 << 8)), EndReason::Exited(1));
+        assert_eq!(EndReason::from_status(ExitStatus::from_raw(11)), EndReason::Crashed(11));
+        assert_eq!(EndReason::Crashed(11).as_string(), "crashed signal 11");
+    }
+
+    #[test]
+    fn test_watch_exit() {
+        let task = Task::new(Model::Pro, ProcType::Video, Autoloop::No, PathBuf::from("/media/advert.mp4"), 5, String::new());
+        let play = Play::begin(&task);
+        let mut player = Command::new("sh").arg("-c").arg("sleep 0.2").spawn().unwrap();
+        play.watch(player.id());
+        assert!(play.ended.get().is_none());
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while play.ended.get().is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        let ended = *play.ended.get().unwrap();
+        assert!(ended >= play.start);
+        // the player can still be reaped with its exit status
+        assert!(player.wait().unwrap().success());
+    }
+
+    #[test]
+    fn test_record_and_summarise() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("advert.mp4");
+        fs::write(&file, "abc").unwrap();
+        let hash = hash_file(&file).unwrap();
+        assert_eq!(hash, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        let task = Task::new(Model::Pro, ProcType::Video, Autoloop::No, file.clone(), 5, String::new());
+        let mut play = Play::begin(&task);
+        let mut lines = Vec::new();
+        for (day, minutes, reason) in [(5, 30, EndReason::Stopped), (6, 45, EndReason::Exited(0)), (9, 10, EndReason::Stopped)] {
+            play.start = London.with_ymd_and_hms(2026, 10, day, 9, 0, 0).unwrap();
+            lines.push(record_line(&play, Some(hash.clone()), play.start + TimeDelta::minutes(minutes), reason));
+        }
+        let record: Value = serde_json::from_str(&lines[1]).unwrap();
+        assert_eq!(record["task"], file.to_string_lossy().to_string());
+        assert_eq!(record["file_hash"], format!("sha256:{}", hash));
+        assert_eq!(record["start"], "2026-10-06T09:00:00+01:00");
+        assert_eq!(record["duration_secs"], 2700);
+        assert_eq!(record["exit_reason"], "exited 0");
+
+        let log_path = dir.path().join("proof_of_play.jsonl");
+        for line in lines.iter() {
+            append(&log_path, line).unwrap();
+        }
+        append(&log_path, "{\"task\":").unwrap();
+        let log = fs::read_to_string(&log_path).unwrap();
+
+        let (totals, unreadable) = summarise(&log, Options { from: date("2026-10-05"), to: date("2026-10-08") });
+        assert_eq!(unreadable, 1);
+        let total = &totals[&file.to_string_lossy().to_string()];
+        assert_eq!(total.plays, 2);
+        assert_eq!(format_duration(total.duration_secs), "1h 15m 00s");
+        assert_eq!(total.reasons, BTreeMap::from([(String::from("exited 0"), 1), (String::from("stopped"), 1)]));
+    }
+
+    #[test]
+    fn test_from_args() {
+        let today = date("2026-10-18");
+        assert_eq!(Options::from_args(args(&[]), || panic!("today is not needed")).unwrap(), None);
+        assert_eq!(Options::from_args(args(&["--preview"]), || panic!("today is not needed")).unwrap(), None);
+        assert_eq!(Options::from_args(args(&["--proof-of-play"]), || today).unwrap(), Some(Options { from: date("2026-10-12"), to: today }));
+        assert_eq!(Options::from_args(args(&["--proof-of-play", "2026-10-01"]), || today).unwrap(), Some(Options { from: date("2026-10-01"), to: today }));
+        assert_eq!(
+            Options::from_args(args(&["--proof-of-play", "2026-09-01", "2026-09-30"]), || today).unwrap(),
+            Some(Options { from: date("2026-09-01"), to: date("2026-09-30") })
+        );
+        assert!(Options::from_args(args(&["--proof-of-play", "01/09/2026"]), || today).is_err());
+        assert!(Options::from_args(args(&["--proof-of-play", "2026-10-02", "2026-10-01"]), || today).is_err());
+    }
+}
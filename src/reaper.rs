@@ -0,0 +1,160 @@
+use std::{
+    fs,
+    thread,
+    time::Duration,
+};
+
+use nix::{
+    sys::{
+        prctl,
+        wait::{
+            self,
+            WaitPidFlag
+        }
+    },
+    unistd::{
+        self,
+        Pid
+    },
+};
+
+use crate::{
+    logi,
+    logw,
+    logd
+};
+use log::{
+    info,
+    warn,
+    debug
+};
+
+/// How often orphaned processes are checked for and reaped
+const REAP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The parts of `/proc/<pid>/stat` needed to decide whether a process can be reaped
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ProcStat {
+    state: char,
+    parent: i32,
+    group: i32,
+}
+
+/// Parses `/proc/<pid>/stat`. The command name is in brackets and may contain spaces, so the
+/// fields are read from after the last closing bracket.
+fn parse_stat(stat: &str) -> Option<ProcStat> {
+    let mut fields = stat.rsplit_once(')')?.1.split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let parent = fields.next()?.parse().ok()?;
+    let group = fields.next()?.parse().ok()?;
+    Some(ProcStat { state, parent, group })
+}
+
+/// True for a finished orphan from one of the task's process groups. The tasks lead their
+/// groups and are reaped by their own `Child`, and anything in our own group was started by
+/// us and is waited for where it was started, so both are left alone.
+fn is_orphan(pid: i32, stat: &ProcStat, own_pid: i32, own_group: i32) -> bool {
+    stat.state == 'Z'
+        && stat.parent == own_pid
+        && stat.group != own_group
+        && stat.group != pid
+}
+
+/// Reaps every finished orphan left behind by a task, returning how many were reaped
+fn reap_orphans() -> usize {
+    let own_pid = unistd::getpid().as_raw();
+    let own_group = unistd::getpgrp().as_raw();
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return 0;
+    };
+
+    let mut reaped = 0;
+    for entry in entries.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<i32>().ok()) else {
+            continue;
+        };
+        let Some(stat) = fs::read_to_string(entry.path().join("stat")).ok().and_then(|stat| parse_stat(&stat)) else {
+            continue;
+        };
+        if is_orphan(pid, &stat, own_pid, own_group)
+            && wait::waitpid(Pid::from_raw(pid), Some(WaitPidFlag::WNOHANG)).is_ok() {
+            logd!("Reaped orphaned process {}", pid);
+            reaped += 1;
+        }
+    }
+    reaped
+}
+
+/// Makes this process the reaper for everything the tasks start, so that processes left
+/// behind when a browser or script exits come back here rather than to init, and reaps them
+/// in the background.
+pub fn start() {
+    if let Err(e) = prctl::set_child_subreaper(true) {
+        logw!("Failed to become the reaper for task subprocesses: {}", e);
+        return;
+    }
+    logi!("Reaping orphaned task subprocesses every {}s", REAP_INTERVAL.as_secs());
+    thread::spawn(|| loop {
+        thread::sleep(REAP_INTERVAL);
+        reap_orphans();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{
+            BufRead,
+            BufReader
+        },
+        os::unix::process::CommandExt,
+        process::{
+            Command,
+            Stdio
+        },
+        time::Instant
+    };
+
+    #[test]
+    fn test_parse_stat() {
+        let stat = "4242 (Web Content) Z 1000 4200 4200 0 -1 4194560 87 0 0 0";
+        assert_eq!(parse_stat(stat), Some(ProcStat { state: 'Z', parent: 1000, group: 4200 }));
+        assert_eq!(parse_stat("4242 (sh) S"), None);
+        assert_eq!(parse_stat(""), None);
+
+        let orphan = ProcStat { state: 'Z', parent: 1000, group: 4200 };
+        assert!(is_orphan(4242, &orphan, 1000, 1000));
+        // the task itself is reaped by its Child
+        assert!(!is_orphan(4200, &orphan, 1000, 1000));
+        // started by us, not by a task
+        assert!(!is_orphan(4242, &ProcStat { group: 1000, ..orphan }, 1000, 1000));
+        assert!(!is_orphan(4242, &ProcStat { state: 'S', ..orphan }, 1000, 1000));
+        assert!(!is_orphan(4242, &ProcStat { parent: 1, ..orphan }, 1000, 1000));
+    }
+
+    #[test]
+    fn test_reap_orphans() {
+        prctl::set_child_subreaper(true).unwrap();
+        // a script that exits and leaves a subprocess behind
+        let mut script = Command::new("sh")
+            .arg("-c")
+            .arg("sleep 0.2 & echo $!")
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(script.stdout.take().unwrap()).read_line(&mut line).unwrap();
+        let orphan: i32 = line.trim().parse().unwrap();
+        script.wait().unwrap();
+
+        let stat_path = format!("/proc/{}/stat", orphan);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while fs::metadata(&stat_path).is_ok() && Instant::now() < deadline {
+            reap_orphans();
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert!(fs::metadata(&stat_path).is_err());
+    }
+}
@@ -0,0 +1,1128 @@
+use std::fmt;
+
+use chrono::{
+    DateTime,
+    Datelike,
+    LocalResult,
+    NaiveDate,
+    NaiveDateTime,
+    NaiveTime,
+    TimeDelta,
+    TimeZone,
+    Timelike,
+    Utc,
+    Weekday as ChronoWeekday
+};
+use chrono_tz::Tz;
+
+use crate::{
+    logi,
+    logw
+};
+use log::{
+    info,
+    warn
+};
+
+use crate::{
+    Weekday,
+    clock::{
+        Clock,
+        SystemClock,
+        Wake
+    },
+    cron::CronSchedule,
+    error::MediatimerError,
+    solar::{
+        self,
+        Location,
+        SolarEvent
+    }
+};
+
+/// The number of upcoming events reported after each scheduler wake up
+const UPCOMING_LIMIT: usize = 5;
+
+/// How many days ahead or behind to search for events, widening if none are found
+const SEARCH_DAYS: [i64; 3] = [7, 31, 366];
+
+/// How far past a nonexistent local time to search for the end of a DST gap
+const DST_GAP_SEARCH_MINUTES: i64 = 180;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Start,
+    Stop,
+    /// Start the interrupt at this index, pre-empting the scheduled task
+    Interrupt(usize),
+    /// End the interrupt at this index and hand back to the scheduled task
+    InterruptEnd(usize),
+}
+
+impl Action {
+    /// The order events at the same instant are handled in. Stops come before starts so
+    /// back-to-back windows hand over, and interrupts come last so they pre-empt whatever
+    /// the regular programme has just started.
+    fn order(&self) -> u8 {
+        match self {
+            Action::Stop => 0,
+            Action::InterruptEnd(_) => 1,
+            Action::Start => 2,
+            Action::Interrupt(_) => 3
+        }
+    }
+
+    fn is_window(&self) -> bool {
+        matches!(self, Action::Start | Action::Stop)
+    }
+}
+
+/// When an interrupt runs: at the same time every day, or once at a date and time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InterruptTime {
+    Daily(NaiveTime),
+    Once(NaiveDateTime),
+}
+
+impl InterruptTime {
+    /// Parses "HH:MM:SS" for a daily interrupt or "YYYY-MM-DD HH:MM:SS" for a one-off
+    pub fn parse(value: &str) -> Result<InterruptTime, MediatimerError> {
+        let value = value.trim();
+        if let Ok(once) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+            return Ok(InterruptTime::Once(once));
+        }
+        Ok(InterruptTime::Daily(parse_time(value)?))
+    }
+}
+
+/// The timing of an interrupt event. Without a duration the interrupt runs until its
+/// player exits.
+#[derive(Debug, Clone)]
+pub struct InterruptSchedule {
+    pub at: InterruptTime,
+    pub duration: Option<TimeDelta>,
+}
+
+/// A single start or stop event at a point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledEvent {
+    pub at: DateTime<Tz>,
+    pub action: Action,
+    /// The start of the window this event belongs to. This is used to calculate the seek
+    /// position when a task is started late.
+    pub window_start: DateTime<Tz>,
+}
+
+impl fmt::Display for ScheduledEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.action {
+            Action::Start => String::from("start"),
+            Action::Stop => String::from("stop"),
+            Action::Interrupt(index) => format!("interrupt {}", index),
+            Action::InterruptEnd(index) => format!("end interrupt {}", index)
+        };
+        write!(f, "{} {}", action, self.at.format("%a %Y-%m-%d %H:%M:%S %z"))
+    }
+}
+
+/// One end of a window: either a wall clock time or an offset from sunrise or sunset
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bound {
+    Fixed(NaiveTime),
+    Solar(SolarEvent, TimeDelta),
+}
+
+/// Plays the task every `every` inside a window. Each repeat either stops after `play_for`
+/// or, without it, plays once and is left to finish on its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Repeat {
+    every: TimeDelta,
+    play_for: Option<TimeDelta>,
+}
+
+#[derive(Debug, Clone)]
+struct Window {
+    day: ChronoWeekday,
+    start: Bound,
+    end: Bound,
+    repeat: Option<Repeat>,
+}
+
+/// The weekly timetable, built from the `MT_<DAY>` schedule variables.
+/// Windows that end at or before their start time are treated as running past midnight and
+/// stop on the following day. All times are wall clock times in the timetable's timezone.
+/// Sunrise and sunset bounds are calculated for each date from the timetable's location.
+/// A window may repeat the task at an interval, giving a start for each repeat.
+/// Cron entries add start or stop events of their own alongside the weekly windows.
+/// Seasonal blocks replace the weekly windows with their own for the dates they cover.
+#[derive(Debug, Clone)]
+pub struct Timetable {
+    tz: Tz,
+    location: Option<Location>,
+    windows: Vec<Window>,
+    seasons: Vec<SeasonBlock>,
+    cron: Vec<(Action, CronSchedule)>,
+    interrupts: Vec<InterruptSchedule>,
+}
+
+/// A weekly schedule that is only in effect between two dates, inclusive. A missing date
+/// leaves that end of the season open.
+#[derive(Debug, Clone)]
+pub struct Season {
+    pub name: String,
+    pub valid_from: Option<NaiveDate>,
+    pub valid_until: Option<NaiveDate>,
+    pub days: Vec<Weekday>,
+}
+
+impl Season {
+    pub fn new(name: &str) -> Season {
+        Season {
+            name: String::from(name),
+            valid_from: None,
+            valid_until: None,
+            days: Vec::new()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SeasonBlock {
+    name: String,
+    valid_from: Option<NaiveDate>,
+    valid_until: Option<NaiveDate>,
+    windows: Vec<Window>,
+}
+
+impl SeasonBlock {
+    fn covers(&self, date: NaiveDate) -> bool {
+        self.valid_from.is_none_or(|from| date >= from)
+            && self.valid_until.is_none_or(|until| date <= until)
+    }
+}
+
+/// Parses "HH:MM:SS" or "HH:MM" into a time
+fn parse_time(value: &str) -> Result<NaiveTime, MediatimerError> {
+    let value = value.trim();
+    let time = NaiveTime::parse_from_str(value, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))?;
+    Ok(time)
+}
+
+/// Parses a window bound such as "18:00:00", "sunset", "sunset-00:30" or "sunrise+01:00"
+fn parse_bound(value: &str) -> Result<Bound, MediatimerError> {
+    let value = value.trim().to_lowercase();
+    for (name, event) in [("sunrise", SolarEvent::Sunrise), ("sunset", SolarEvent::Sunset)] {
+        if let Some(offset) = value.strip_prefix(name) {
+            if offset.is_empty() {
+                return Ok(Bound::Solar(event, TimeDelta::zero()));
+            }
+            let (sign, time) = offset.split_at(1);
+            let delta = TimeDelta::seconds(parse_time(time)?.num_seconds_from_midnight() as i64);
+            return match sign {
+                "+" => Ok(Bound::Solar(event, delta)),
+                "-" => Ok(Bound::Solar(event, -delta)),
+                _ => Err(MediatimerError::Schedule(format!("Solar offset incorrectly formatted: {}", value)))
+            };
+        }
+    }
+    Ok(Bound::Fixed(parse_time(&value)?))
+}
+
+/// Parses an interval such as "20m", "90s" or "1h". A bare number is in minutes.
+fn parse_interval(value: &str) -> Result<TimeDelta, MediatimerError> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "m")
+    };
+    let number = number.parse::<i64>()
+        .map_err(|_| MediatimerError::Schedule(format!("Interval incorrectly formatted: {}", value)))?;
+    let interval = match unit {
+        "s" => TimeDelta::seconds(number),
+        "m" => TimeDelta::minutes(number),
+        "h" => TimeDelta::hours(number),
+        _ => return Err(MediatimerError::Schedule(format!("Interval unit not recognised: {}", value)))
+    };
+    if interval <= TimeDelta::zero() {
+        return Err(MediatimerError::Schedule(format!("Interval must be greater than zero: {}", value)));
+    }
+    Ok(interval)
+}
+
+/// Splits a window end such as "17:00:00 every 20m for 5m" into the bound and repeat rule
+fn parse_window_end(value: &str) -> Result<(Bound, Option<Repeat>), MediatimerError> {
+    let Some((end, rule)) = value.split_once(" every ") else {
+        return Ok((parse_bound(value)?, None));
+    };
+    let (every, play_for) = match rule.split_once(" for ") {
+        Some((every, play_for)) => (parse_interval(every)?, Some(parse_interval(play_for)?)),
+        None => (parse_interval(rule)?, None)
+    };
+    Ok((parse_bound(end)?, Some(Repeat { every, play_for })))
+}
+
+/// Converts a local date and time to a timestamp, handling daylight saving transitions.
+///
+/// When the clocks go back a local time happens twice; the first occurrence is used so that
+/// a window is never shortened or run twice. When the clocks go forward a local time may not
+/// exist at all; the event then happens at the end of the gap, the first moment the local time
+/// exists again.
+fn resolve_local(tz: &Tz, naive: NaiveDateTime) -> Option<DateTime<Tz>> {
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(dt) => Some(dt),
+        LocalResult::Ambiguous(earliest, latest) => {
+            logi!("Local time {} is ambiguous ({} or {}), using the first occurrence", naive, earliest, latest);
+            Some(earliest)
+        },
+        LocalResult::None => {
+            let resolved = (1..=DST_GAP_SEARCH_MINUTES)
+                .filter_map(|m| naive.checked_add_signed(TimeDelta::minutes(m)))
+                .map(|later| later.with_second(0).unwrap_or(later))
+                .find_map(|later| tz.from_local_datetime(&later).earliest());
+            match resolved {
+                Some(dt) => logi!("Local time {} does not exist, using {}", naive, dt),
+                None => logw!("Local time {} does not exist and could not be resolved, skipping", naive)
+            }
+            resolved
+        }
+    }
+}
+
+impl Timetable {
+    pub fn new(tz: Tz, location: Option<Location>) -> Timetable {
+        Timetable {
+            tz,
+            location,
+            windows: Vec::new(),
+            seasons: Vec::new(),
+            cron: Vec::new(),
+            interrupts: Vec::new()
+        }
+    }
+
+    /// Adds an interrupt, returning the index its events refer to
+    pub fn add_interrupt(&mut self, interrupt: InterruptSchedule) -> usize {
+        logi!("Interrupt {} at {:?}", self.interrupts.len(), interrupt.at);
+        self.interrupts.push(interrupt);
+        self.interrupts.len() - 1
+    }
+
+    /// Adds cron expressions that raise `action` each time they run
+    pub fn add_cron(&mut self, action: Action, schedules: &[CronSchedule]) {
+        for schedule in schedules.iter() {
+            logi!("Cron {:?} entry: {}", action, schedule);
+            self.cron.push((action, schedule.clone()));
+        }
+    }
+
+    /// Adds the windows from each day of the weekly schedule
+    pub fn add_weekdays(&mut self, days: &[Weekday]) -> Result<(), MediatimerError> {
+        let windows = self.parse_windows(days)?;
+        self.windows.extend(windows);
+        Ok(())
+    }
+
+    /// Adds a seasonal block. Once any block has been added the weekly schedule from
+    /// `add_weekdays` is no longer used, and dates outside every block have no windows.
+    pub fn add_season(&mut self, season: &Season) -> Result<(), MediatimerError> {
+        if let (Some(from), Some(until)) = (season.valid_from, season.valid_until)
+            && from > until {
+            return Err(MediatimerError::Schedule(format!("Season {} ends before it starts", season.name)));
+        }
+        logi!("Season {} runs from {:?} until {:?}", season.name, season.valid_from, season.valid_until);
+        let windows = self.parse_windows(&season.days)?;
+        self.seasons.push(SeasonBlock {
+            name: season.name.clone(),
+            valid_from: season.valid_from,
+            valid_until: season.valid_until,
+            windows
+        });
+        Ok(())
+    }
+
+    fn parse_windows(&self, days: &[Weekday]) -> Result<Vec<Window>, MediatimerError> {
+        let mut windows = Vec::new();
+        for day in days.iter() {
+            let (chrono_day, timings) = match day {
+                Weekday::Monday(t) => (ChronoWeekday::Mon, t),
+                Weekday::Tuesday(t) => (ChronoWeekday::Tue, t),
+                Weekday::Wednesday(t) => (ChronoWeekday::Wed, t),
+                Weekday::Thursday(t) => (ChronoWeekday::Thu, t),
+                Weekday::Friday(t) => (ChronoWeekday::Fri, t),
+                Weekday::Saturday(t) => (ChronoWeekday::Sat, t),
+                Weekday::Sunday(t) => (ChronoWeekday::Sun, t)
+            };
+            logi!("{} has {} scheduled windows", day.as_str(), timings.len());
+            for (start, end) in timings.iter() {
+                let (end, repeat) = parse_window_end(end)?;
+                let window = Window {
+                    day: chrono_day,
+                    start: parse_bound(start)?,
+                    end,
+                    repeat,
+                };
+                let solar = [window.start, window.end].iter().any(|b| matches!(b, Bound::Solar(..)));
+                if solar && self.location.is_none() {
+                    return Err(MediatimerError::Schedule(String::from("Sunrise and sunset times need MT_LATITUDE and MT_LONGITUDE to be set")));
+                }
+                windows.push(window);
+            }
+        }
+        Ok(windows)
+    }
+
+    /// The windows in effect on a date. Where seasons overlap, the one that started most
+    /// recently is used.
+    fn windows_on(&self, date: NaiveDate) -> &[Window] {
+        if self.seasons.is_empty() {
+            return &self.windows;
+        }
+        self.season_block_on(date)
+            .map(|s| s.windows.as_slice())
+            .unwrap_or(&[])
+    }
+
+    fn season_block_on(&self, date: NaiveDate) -> Option<&SeasonBlock> {
+        self.seasons.iter()
+            .filter(|s| s.covers(date))
+            .max_by_key(|s| s.valid_from)
+    }
+
+    /// The name of the season in effect on a date, if any
+    pub fn season_on(&self, date: NaiveDate) -> Option<&str> {
+        self.season_block_on(date).map(|s| s.name.as_str())
+    }
+
+    /// The instant a bound falls at on the given date
+    fn resolve_bound(&self, bound: Bound, date: NaiveDate) -> Option<DateTime<Tz>> {
+        match bound {
+            Bound::Fixed(time) => resolve_local(&self.tz, date.and_time(time)),
+            Bound::Solar(event, offset) => {
+                let location = self.location?;
+                let resolved = solar::event_time(date, location, event)
+                    .map(|t| t.with_timezone(&self.tz) + offset);
+                if resolved.is_none() {
+                    logw!("No {:?} on {} at this location, skipping", event, date);
+                }
+                resolved
+            }
+        }
+    }
+
+    /// All events belonging to windows that open on the given date, and cron and interrupt
+    /// events on it
+    fn events_from(&self, date: NaiveDate) -> Vec<ScheduledEvent> {
+        let mut events = Vec::new();
+        for window in self.windows_on(date).iter().filter(|w| w.day == date.weekday()) {
+            let Some(window_start) = self.resolve_bound(window.start, date) else {
+                continue;
+            };
+            let mut window_end = self.resolve_bound(window.end, date);
+            if window_end.is_some_and(|end| end <= window_start) {
+                window_end = date.succ_opt().and_then(|next| self.resolve_bound(window.end, next));
+            }
+            match (window.repeat, window_end) {
+                (Some(repeat), Some(window_end)) => events.extend(repeat_events(repeat, window_start, window_end)),
+                _ => events.push(ScheduledEvent { at: window_start, action: Action::Start, window_start })
+            }
+            if let Some(at) = window_end {
+                events.push(ScheduledEvent { at, action: Action::Stop, window_start });
+            }
+        }
+        for (action, schedule) in self.cron.iter().filter(|(_, c)| c.matches_date(date)) {
+            for time in schedule.times() {
+                if let Some(at) = resolve_local(&self.tz, date.and_time(time)) {
+                    events.push(ScheduledEvent { at, action: *action, window_start: at });
+                }
+            }
+        }
+        for (index, interrupt) in self.interrupts.iter().enumerate() {
+            let start = match interrupt.at {
+                InterruptTime::Daily(time) => resolve_local(&self.tz, date.and_time(time)),
+                InterruptTime::Once(at) if at.date() == date => resolve_local(&self.tz, at),
+                InterruptTime::Once(_) => None
+            };
+            let Some(start) = start else {
+                continue;
+            };
+            events.push(ScheduledEvent { at: start, action: Action::Interrupt(index), window_start: start });
+            if let Some(duration) = interrupt.duration {
+                events.push(ScheduledEvent { at: start + duration, action: Action::InterruptEnd(index), window_start: start });
+            }
+        }
+        events
+    }
+
+    /// Every event belonging to dates between `first` and `last` days from `from`, in time
+    /// order.
+    fn events_between(&self, from: DateTime<Tz>, first: i64, last: i64) -> Vec<ScheduledEvent> {
+        let today = from.date_naive();
+        let mut events = Vec::new();
+        for offset in first..=last {
+            if let Some(date) = today.checked_add_signed(TimeDelta::days(offset)) {
+                events.extend(self.events_from(date));
+            }
+        }
+        // stops sort before starts at the same instant, so back-to-back windows hand over
+        events.sort_by_key(|e| (e.at, e.action.order()));
+        events
+    }
+
+    /// The current time in the timetable's timezone
+    pub fn now(&self) -> DateTime<Tz> {
+        Utc::now().with_timezone(&self.tz)
+    }
+
+    /// The next events strictly after `now`
+    ///
+    /// Weekly windows always have an event within a week, but cron entries may run as rarely
+    /// as once a year, so the search widens until enough events are found.
+    pub fn upcoming(&self, now: DateTime<Tz>, limit: usize) -> Vec<ScheduledEvent> {
+        let mut upcoming = Vec::new();
+        for days in SEARCH_DAYS {
+            upcoming = self.events_between(now, -1, days)
+                .into_iter()
+                .filter(|e| e.at > now)
+                .take(limit)
+                .collect::<Vec<_>>();
+            if upcoming.len() >= limit {
+                break;
+            }
+        }
+        upcoming
+    }
+
+    /// Every event from `from` up to but not including `until`, in time order
+    pub fn timeline(&self, from: DateTime<Tz>, until: DateTime<Tz>) -> Vec<ScheduledEvent> {
+        let days = (until.date_naive() - from.date_naive()).num_days();
+        self.events_between(from, -1, days)
+            .into_iter()
+            .filter(|e| e.at >= from && e.at < until)
+            .collect()
+    }
+
+    /// Every event from `from` up to and including `now`, in the order they are handled
+    pub fn due(&self, from: DateTime<Tz>, now: DateTime<Tz>) -> Vec<ScheduledEvent> {
+        let days = (now.date_naive() - from.date_naive()).num_days();
+        self.events_between(from, -1, days)
+            .into_iter()
+            .filter(|e| e.at >= from && e.at <= now)
+            .collect()
+    }
+
+    /// If `now` falls inside a window, returns the start of that window. This looks back
+    /// for the most recent event, which decides whether the task should be running.
+    pub fn active_window(&self, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        for days in SEARCH_DAYS {
+            let latest = self.events_between(now, -days, 0)
+                .into_iter()
+                .rev()
+                .find(|e| e.at <= now && e.action.is_window());
+            if let Some(latest) = latest {
+                return Some(latest)
+                    .filter(|e| e.action == Action::Start)
+                    .map(|e| e.window_start);
+            }
+        }
+        None
+    }
+}
+
+/// Expands a repeat rule into a start for every repeat in the window, each seeking from its
+/// own start. A repeat with a duration stops early unless the next repeat replaces it first.
+/// The stop at the end of the window is added by the caller.
+fn repeat_events(repeat: Repeat, window_start: DateTime<Tz>, window_end: DateTime<Tz>) -> Vec<ScheduledEvent> {
+    let mut events = Vec::new();
+    let mut at = window_start;
+    while at < window_end {
+        events.push(ScheduledEvent { at, action: Action::Start, window_start: at });
+        if let Some(play_for) = repeat.play_for
+            && play_for < repeat.every
+            && at + play_for < window_end {
+            events.push(ScheduledEvent { at: at + play_for, action: Action::Stop, window_start: at });
+        }
+        at += repeat.every;
+    }
+    events
+}
+
+/// Works out whether `now` falls inside a window and raises a start or stop event if that
+/// differs from `in_window`. Returns whether the task should now be running.
+fn reconcile<F>(timetable: &Timetable, now: DateTime<Tz>, in_window: bool, on_event: &mut F) -> bool
+where
+    F: FnMut(&ScheduledEvent)
+{
+    match timetable.active_window(now) {
+        Some(window_start) if !in_window => {
+            logi!("Inside the window that started at {}, starting task", window_start);
+            on_event(&ScheduledEvent { at: now, action: Action::Start, window_start });
+            true
+        },
+        None if in_window => {
+            logi!("Outside of all windows, stopping task");
+            on_event(&ScheduledEvent { at: now, action: Action::Stop, window_start: now });
+            false
+        },
+        active => active.is_some()
+    }
+}
+
+/// Steps through a timetable against a clock, raising each event as it falls due. Events are
+/// re-evaluated from the clock after every wake up, so events are never fired for time that
+/// was skipped over by a clock change.
+///
+/// On start up, and whenever the wall clock is corrected (for example by an NTP sync after
+/// boot), the window that should be active is worked out again so that the task is started
+/// part way through a window or stopped if the correction moved the clock past its end.
+pub struct Scheduler<'a, C: Clock> {
+    timetable: &'a Timetable,
+    clock: C,
+    in_window: bool,
+    /// Every event up to this time has been raised. Handling an event can take a few
+    /// seconds, so the next events are looked for from here rather than from the clock.
+    handled: DateTime<Tz>,
+}
+
+impl<'a, C: Clock> Scheduler<'a, C> {
+    pub fn new(timetable: &'a Timetable, clock: C) -> Scheduler<'a, C> {
+        let handled = clock.now().with_timezone(&timetable.tz);
+        Scheduler {
+            timetable,
+            clock,
+            in_window: false,
+            handled,
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Tz> {
+        self.clock.now().with_timezone(&self.timetable.tz)
+    }
+
+    /// Starts the task straight away if the clock is already inside a window
+    pub fn start<F>(&mut self, on_event: &mut F)
+    where
+        F: FnMut(&ScheduledEvent)
+    {
+        self.handled = self.now();
+        self.in_window = reconcile(self.timetable, self.handled, false, on_event);
+    }
+
+    /// The next events that have not been raised yet
+    pub fn upcoming(&self, limit: usize) -> Vec<ScheduledEvent> {
+        self.timetable.upcoming(self.handled, limit)
+    }
+
+    /// Sleeps until the next event and raises it, or re-evaluates the schedule if the clock
+    /// jumped while sleeping. Every event due by the time it wakes is raised in order, so
+    /// events at the same instant, such as a stop and the next window's start, all fire, and
+    /// an event that fell due while the last one was being handled is raised straight away.
+    /// Returns false if nothing more is scheduled.
+    pub fn step<F>(&mut self, on_event: &mut F) -> bool
+    where
+        F: FnMut(&ScheduledEvent)
+    {
+        let Some(event) = self.upcoming(1).into_iter().next() else {
+            return false;
+        };
+
+        logi!("Next scheduled event: {}", event);
+        match self.clock.sleep_until(event.at.with_timezone(&Utc)) {
+            Wake::Due => {
+                self.handled = self.now();
+                for event in self.timetable.due(event.at, self.handled) {
+                    logi!("Scheduled event due: {}", event);
+                    on_event(&event);
+                    if event.action.is_window() {
+                        self.in_window = event.action == Action::Start;
+                    }
+                }
+            },
+            Wake::ClockJump(drift) => {
+                logw!("Wall clock jumped by {}s, re-evaluating schedule", drift.num_seconds());
+                self.handled = self.now();
+                self.in_window = reconcile(self.timetable, self.handled, self.in_window, on_event);
+            }
+        }
+        true
+    }
+}
+
+/// Runs the timetable forever against the system clock, calling `on_event` as each event
+/// falls due.
+pub fn run<F>(timetable: &Timetable, mut on_event: F) -> !
+where
+    F: FnMut(&ScheduledEvent)
+{
+    let mut scheduler = Scheduler::new(timetable, SystemClock::default());
+    scheduler.start(&mut on_event);
+    crate::notify::ready();
+    loop {
+        if !timetable.seasons.is_empty() {
+            match timetable.season_on(scheduler.now().date_naive()) {
+                Some(season) => logi!("Season in effect: {}", season),
+                None => logi!("No season in effect today, showing the background only")
+            }
+        }
+        crate::status::write_upcoming(&scheduler.upcoming(UPCOMING_LIMIT));
+
+        if !scheduler.step(&mut on_event) {
+            logw!("Schedule has no upcoming events");
+            crate::notify::idle();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::simulated::SimulatedClock;
+
+    const LONDON: Location = Location { latitude: 51.5074, longitude: -0.1278 };
+
+    fn timetable(days: Vec<Weekday>) -> Timetable {
+        let mut table = Timetable::new(Tz::Europe__London, Some(LONDON));
+        table.add_weekdays(&days).unwrap();
+        table
+    }
+
+    fn next_event(table: &Timetable, now: DateTime<Tz>) -> ScheduledEvent {
+        table.upcoming(now, 1).into_iter().next().unwrap()
+    }
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> DateTime<Tz> {
+        Tz::Europe__London.with_ymd_and_hms(y, m, d, h, min, s).single().unwrap()
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("08:30").unwrap(), NaiveTime::from_hms_opt(8, 30, 0).unwrap());
+        assert_eq!(parse_time("15:45:20").unwrap(), NaiveTime::from_hms_opt(15, 45, 20).unwrap());
+        assert!(parse_time("25:00").is_err());
+    }
+
+    #[test]
+    fn test_next_event() {
+        // 2025-06-02 is a Monday
+        let table = timetable(vec![Weekday::Monday(vec![("10:00:00".to_string(), "12:00:00".to_string())])]);
+
+        let next = next_event(&table, local(2025, 6, 2, 9, 0, 0));
+        assert_eq!(next.action, Action::Start);
+        assert_eq!(next.at, local(2025, 6, 2, 10, 0, 0));
+
+        let next = next_event(&table, local(2025, 6, 2, 11, 0, 0));
+        assert_eq!(next.action, Action::Stop);
+        assert_eq!(next.at, local(2025, 6, 2, 12, 0, 0));
+
+        // after the last window of the week the next event is the following Monday
+        let next = next_event(&table, local(2025, 6, 2, 13, 0, 0));
+        assert_eq!(next.action, Action::Start);
+        assert_eq!(next.at, local(2025, 6, 9, 10, 0, 0));
+    }
+
+    #[test]
+    fn test_upcoming_is_ordered() {
+        let table = timetable(vec![
+            Weekday::Tuesday(vec![("14:00".to_string(), "16:00".to_string())]),
+            Weekday::Monday(vec![("08:00".to_string(), "12:00".to_string())]),
+        ]);
+        let upcoming = table.upcoming(local(2025, 6, 1, 12, 0, 0), 4);
+        let times = upcoming.iter().map(|e| e.at).collect::<Vec<_>>();
+        assert_eq!(times, vec![
+            local(2025, 6, 2, 8, 0, 0),
+            local(2025, 6, 2, 12, 0, 0),
+            local(2025, 6, 3, 14, 0, 0),
+            local(2025, 6, 3, 16, 0, 0),
+        ]);
+    }
+
+    #[test]
+    fn test_active_window() {
+        let table = timetable(vec![Weekday::Monday(vec![("10:00:00".to_string(), "12:00:00".to_string())])]);
+        assert_eq!(table.active_window(local(2025, 6, 2, 11, 0, 0)), Some(local(2025, 6, 2, 10, 0, 0)));
+        assert_eq!(table.active_window(local(2025, 6, 2, 9, 0, 0)), None);
+        assert_eq!(table.active_window(local(2025, 6, 2, 12, 30, 0)), None);
+    }
+
+    #[test]
+    fn test_overnight_window() {
+        let table = timetable(vec![Weekday::Friday(vec![("22:00:00".to_string(), "02:00:00".to_string())])]);
+        // 2025-06-07 is the Saturday after the window opens
+        assert_eq!(table.active_window(local(2025, 6, 7, 1, 0, 0)), Some(local(2025, 6, 6, 22, 0, 0)));
+        let next = next_event(&table, local(2025, 6, 7, 1, 0, 0));
+        assert_eq!(next.action, Action::Stop);
+        assert_eq!(next.at, local(2025, 6, 7, 2, 0, 0));
+    }
+
+    #[test]
+    fn test_nonexistent_local_time() {
+        // clocks go forward at 01:00 on 2025-03-30 in London, so 01:30 does not exist
+        let table = timetable(vec![Weekday::Sunday(vec![("01:30:00".to_string(), "03:00:00".to_string())])]);
+        let next = next_event(&table, local(2025, 3, 29, 12, 0, 0));
+        assert_eq!(next.action, Action::Start);
+        assert_eq!(next.at, local(2025, 3, 30, 2, 0, 0));
+    }
+
+    #[test]
+    fn test_ambiguous_local_time() {
+        // clocks go back at 02:00 on 2025-10-26 in London, so 01:30 happens twice
+        let table = timetable(vec![Weekday::Sunday(vec![("01:30:00".to_string(), "04:00:00".to_string())])]);
+        let next = next_event(&table, local(2025, 10, 25, 12, 0, 0));
+        assert_eq!(next.action, Action::Start);
+        assert_eq!(next.at, Utc.with_ymd_and_hms(2025, 10, 26, 0, 30, 0).single().unwrap());
+
+        // the window is three and a half hours long including the repeated hour
+        let stop = &table.upcoming(local(2025, 10, 25, 12, 0, 0), 2)[1];
+        assert_eq!(stop.at - next.at, TimeDelta::minutes(210));
+    }
+
+    #[test]
+    fn test_timetable_timezone() {
+        let days = vec![Weekday::Monday(vec![("10:00:00".to_string(), "12:00:00".to_string())])];
+        let mut london = Timetable::new(Tz::Europe__London, None);
+        london.add_weekdays(&days).unwrap();
+        let mut new_york = Timetable::new(Tz::America__New_York, None);
+        new_york.add_weekdays(&days).unwrap();
+        let now = local(2025, 6, 2, 9, 0, 0);
+        let london_start = next_event(&london, now).at;
+        let new_york_start = next_event(&new_york, now).at;
+        assert_eq!(new_york_start - london_start, TimeDelta::hours(5));
+    }
+
+    #[test]
+    fn test_parse_bound() {
+        assert_eq!(parse_bound("18:00:00").unwrap(), Bound::Fixed(NaiveTime::from_hms_opt(18, 0, 0).unwrap()));
+        assert_eq!(parse_bound("sunset").unwrap(), Bound::Solar(SolarEvent::Sunset, TimeDelta::zero()));
+        assert_eq!(parse_bound("Sunset-00:30").unwrap(), Bound::Solar(SolarEvent::Sunset, TimeDelta::minutes(-30)));
+        assert_eq!(parse_bound("sunrise+01:00").unwrap(), Bound::Solar(SolarEvent::Sunrise, TimeDelta::hours(1)));
+        assert!(parse_bound("sunset*01:00").is_err());
+    }
+
+    #[test]
+    fn test_solar_window() {
+        let table = timetable(vec![Weekday::Saturday(vec![("sunset-00:30".to_string(), "23:00:00".to_string())])]);
+        // sunset in London on 2025-06-21 is around 21:21 BST
+        let start = next_event(&table, local(2025, 6, 21, 12, 0, 0));
+        assert_eq!(start.action, Action::Start);
+        let expected = local(2025, 6, 21, 20, 51, 0);
+        assert!((start.at - expected).num_minutes().abs() < 3, "unexpected start {}", start.at);
+
+        // the window moves with the sunset from week to week
+        let later = next_event(&table, local(2025, 9, 20, 12, 0, 0));
+        assert_eq!(later.at.date_naive(), NaiveDate::from_ymd_opt(2025, 9, 20).unwrap());
+        assert!(later.at.time() < NaiveTime::from_hms_opt(19, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_solar_window_needs_location() {
+        let days = vec![Weekday::Monday(vec![("sunset".to_string(), "23:00:00".to_string())])];
+        let mut table = Timetable::new(Tz::Europe__London, None);
+        assert!(table.add_weekdays(&days).is_err());
+    }
+
+    #[test]
+    fn test_cron_events() {
+        let mut table = timetable(vec![]);
+        table.add_cron(Action::Start, &crate::cron::parse_list("0 10 * * sun#1").unwrap());
+        table.add_cron(Action::Stop, &crate::cron::parse_list("0 16 * * sun#1").unwrap());
+
+        // 2025-06-01 is the first Sunday of June, the next is 2025-07-06
+        let upcoming = table.upcoming(local(2025, 6, 1, 12, 0, 0), 2);
+        assert_eq!(upcoming[0].action, Action::Stop);
+        assert_eq!(upcoming[0].at, local(2025, 6, 1, 16, 0, 0));
+        assert_eq!(upcoming[1].action, Action::Start);
+        assert_eq!(upcoming[1].at, local(2025, 7, 6, 10, 0, 0));
+    }
+
+    #[test]
+    fn test_cron_active_window() {
+        let mut table = timetable(vec![]);
+        table.add_cron(Action::Start, &crate::cron::parse_list("0 10 1 * *").unwrap());
+        table.add_cron(Action::Stop, &crate::cron::parse_list("0 10 15 * *").unwrap());
+
+        // started on the 1st of the month and runs until the 15th
+        assert_eq!(table.active_window(local(2025, 6, 12, 9, 0, 0)), Some(local(2025, 6, 1, 10, 0, 0)));
+        assert_eq!(table.active_window(local(2025, 6, 20, 9, 0, 0)), None);
+    }
+
+    fn summer_and_winter() -> Timetable {
+        let mut table = timetable(vec![]);
+        let mut summer = Season::new("SUMMER");
+        summer.valid_from = NaiveDate::from_ymd_opt(2025, 4, 1);
+        summer.valid_until = NaiveDate::from_ymd_opt(2025, 9, 30);
+        summer.days = vec![Weekday::Monday(vec![("10:00:00".to_string(), "18:00:00".to_string())])];
+        let mut winter = Season::new("WINTER");
+        winter.valid_from = NaiveDate::from_ymd_opt(2025, 10, 1);
+        winter.valid_until = NaiveDate::from_ymd_opt(2025, 12, 31);
+        winter.days = vec![Weekday::Monday(vec![("11:00:00".to_string(), "16:00:00".to_string())])];
+        table.add_season(&summer).unwrap();
+        table.add_season(&winter).unwrap();
+        table
+    }
+
+    #[test]
+    fn test_season_in_effect() {
+        let table = summer_and_winter();
+        // 2025-09-29 is the last Monday of summer, 2025-10-06 the first of winter
+        assert_eq!(table.season_on(NaiveDate::from_ymd_opt(2025, 9, 29).unwrap()), Some("SUMMER"));
+        let summer = next_event(&table, local(2025, 9, 29, 8, 0, 0));
+        assert_eq!(summer.at, local(2025, 9, 29, 10, 0, 0));
+        let winter = next_event(&table, local(2025, 9, 29, 19, 0, 0));
+        assert_eq!(winter.at, local(2025, 10, 6, 11, 0, 0));
+    }
+
+    #[test]
+    fn test_outside_all_seasons() {
+        let mut table = summer_and_winter();
+        // the weekly schedule is ignored once seasons are configured
+        table.add_weekdays(&[Weekday::Monday(vec![("08:00:00".to_string(), "20:00:00".to_string())])]).unwrap();
+        assert_eq!(table.season_on(NaiveDate::from_ymd_opt(2025, 3, 3).unwrap()), None);
+        assert_eq!(table.active_window(local(2025, 3, 3, 12, 0, 0)), None);
+        assert_eq!(next_event(&table, local(2025, 3, 3, 12, 0, 0)).at, local(2025, 4, 7, 10, 0, 0));
+    }
+
+    #[test]
+    fn test_season_dates_out_of_order() {
+        let mut table = timetable(vec![]);
+        let mut season = Season::new("BACKWARDS");
+        season.valid_from = NaiveDate::from_ymd_opt(2025, 9, 30);
+        season.valid_until = NaiveDate::from_ymd_opt(2025, 4, 1);
+        assert!(table.add_season(&season).is_err());
+    }
+
+    #[test]
+    fn test_interrupt_events() {
+        let mut table = timetable(vec![Weekday::Monday(vec![("10:00:00".to_string(), "14:00:00".to_string())])]);
+        let daily = table.add_interrupt(InterruptSchedule {
+            at: InterruptTime::parse("12:00:00").unwrap(),
+            duration: Some(TimeDelta::minutes(2)),
+        });
+        let once = table.add_interrupt(InterruptSchedule {
+            at: InterruptTime::parse("2025-06-02 13:00:00").unwrap(),
+            duration: None,
+        });
+
+        let upcoming = table.upcoming(local(2025, 6, 2, 11, 0, 0), 4);
+        let actions = upcoming.iter().map(|e| (e.action, e.at)).collect::<Vec<_>>();
+        assert_eq!(actions, vec![
+            (Action::Interrupt(daily), local(2025, 6, 2, 12, 0, 0)),
+            (Action::InterruptEnd(daily), local(2025, 6, 2, 12, 2, 0)),
+            (Action::Interrupt(once), local(2025, 6, 2, 13, 0, 0)),
+            (Action::Stop, local(2025, 6, 2, 14, 0, 0)),
+        ]);
+
+        // interrupts do not change which window is active
+        assert_eq!(table.active_window(local(2025, 6, 2, 12, 1, 0)), Some(local(2025, 6, 2, 10, 0, 0)));
+
+        // the one-off interrupt does not repeat the next day
+        let tuesday = table.upcoming(local(2025, 6, 3, 0, 0, 0), 3);
+        assert!(tuesday.iter().all(|e| e.action != Action::Interrupt(once)));
+    }
+
+    #[test]
+    fn test_parse_window_end() {
+        assert_eq!(parse_window_end("17:00:00").unwrap(), (Bound::Fixed(NaiveTime::from_hms_opt(17, 0, 0).unwrap()), None));
+        let (_, repeat) = parse_window_end("17:00:00 every 20m for 90s").unwrap();
+        assert_eq!(repeat, Some(Repeat { every: TimeDelta::minutes(20), play_for: Some(TimeDelta::seconds(90)) }));
+        let (_, repeat) = parse_window_end("sunset every 1h").unwrap();
+        assert_eq!(repeat, Some(Repeat { every: TimeDelta::hours(1), play_for: None }));
+        assert!(parse_window_end("17:00:00 every 0m").is_err());
+        assert!(parse_window_end("17:00:00 every 5d").is_err());
+    }
+
+    #[test]
+    fn test_repeat_with_duration() {
+        let table = timetable(vec![Weekday::Monday(vec![("10:00:00".to_string(), "11:00:00 every 20m for 5m".to_string())])]);
+        let upcoming = table.upcoming(local(2025, 6, 2, 9, 0, 0), 7);
+        let actions = upcoming.iter().map(|e| (e.action, e.at)).collect::<Vec<_>>();
+        assert_eq!(actions, vec![
+            (Action::Start, local(2025, 6, 2, 10, 0, 0)),
+            (Action::Stop, local(2025, 6, 2, 10, 5, 0)),
+            (Action::Start, local(2025, 6, 2, 10, 20, 0)),
+            (Action::Stop, local(2025, 6, 2, 10, 25, 0)),
+            (Action::Start, local(2025, 6, 2, 10, 40, 0)),
+            (Action::Stop, local(2025, 6, 2, 10, 45, 0)),
+            (Action::Stop, local(2025, 6, 2, 11, 0, 0)),
+        ]);
+
+        // booting part way through a repeat seeks from the start of that repeat
+        assert_eq!(table.active_window(local(2025, 6, 2, 10, 22, 0)), Some(local(2025, 6, 2, 10, 20, 0)));
+        // and between repeats nothing plays
+        assert_eq!(table.active_window(local(2025, 6, 2, 10, 30, 0)), None);
+    }
+
+    #[test]
+    fn test_repeat_once() {
+        let table = timetable(vec![Weekday::Monday(vec![("10:00:00".to_string(), "17:00:00 every 20m".to_string())])]);
+        let events = table.events_between(local(2025, 6, 2, 0, 0, 0), 0, 0);
+        assert_eq!(events.iter().filter(|e| e.action == Action::Start).count(), 21);
+        assert_eq!(events.iter().filter(|e| e.action == Action::Stop).count(), 1);
+        assert_eq!(table.active_window(local(2025, 6, 2, 16, 59, 0)), Some(local(2025, 6, 2, 16, 40, 0)));
+        assert_eq!(table.active_window(local(2025, 6, 2, 17, 0, 0)), None);
+    }
+
+    /// Runs the scheduler against a simulated clock from `clock`'s current time until
+    /// `until`, returning every event raised as (time raised, action, window start)
+    fn simulate(table: &Timetable, clock: SimulatedClock, until: DateTime<Tz>) -> Vec<(DateTime<Tz>, Action, DateTime<Tz>)> {
+        simulate_slow_start(table, &clock, until, TimeDelta::zero())
+    }
+
+    /// As `simulate`, with every start taking `takes` to handle, as a player being replaced
+    /// can take a few seconds to stop
+    fn simulate_slow_start(table: &Timetable, clock: &SimulatedClock, until: DateTime<Tz>, takes: TimeDelta) -> Vec<(DateTime<Tz>, Action, DateTime<Tz>)> {
+        let mut raised = Vec::new();
+        let mut scheduler = Scheduler::new(table, clock);
+        let mut record = |event: &ScheduledEvent| {
+            raised.push((event.at, event.action, event.window_start));
+            if event.action == Action::Start {
+                clock.advance(takes);
+            }
+        };
+        scheduler.start(&mut record);
+        while scheduler.upcoming(1).first().is_some_and(|e| e.at < until) {
+            scheduler.step(&mut record);
+        }
+        raised
+    }
+
+    fn office_hours() -> Vec<Weekday> {
+        let weekday = || vec![("09:00:00".to_string(), "17:00:00".to_string())];
+        vec![
+            Weekday::Monday(weekday()),
+            Weekday::Tuesday(weekday()),
+            Weekday::Wednesday(weekday()),
+            Weekday::Thursday(weekday()),
+            Weekday::Friday(weekday()),
+            Weekday::Saturday(vec![("22:00:00".to_string(), "02:00:00".to_string())]),
+            Weekday::Sunday(vec![("12:00:00".to_string(), "13:00:00".to_string())]),
+        ]
+    }
+
+    #[test]
+    fn test_simulated_week() {
+        let table = timetable(office_hours());
+        // boot late on a Thursday, part way through the window, in the week the clocks go
+        // back at 02:00 on Sunday 2025-10-26
+        let boot = local(2025, 10, 23, 10, 30, 0);
+        let clock = SimulatedClock::new(boot.with_timezone(&Utc));
+        let raised = simulate(&table, clock, local(2025, 10, 30, 10, 30, 0));
+
+        let day = |d: u32, start: u32, end: u32| vec![
+            (local(2025, 10, d, start, 0, 0), Action::Start, local(2025, 10, d, start, 0, 0)),
+            (local(2025, 10, d, end, 0, 0), Action::Stop, local(2025, 10, d, start, 0, 0)),
+        ];
+        let mut expected = vec![
+            (boot, Action::Start, local(2025, 10, 23, 9, 0, 0)),
+            (local(2025, 10, 23, 17, 0, 0), Action::Stop, local(2025, 10, 23, 9, 0, 0)),
+        ];
+        expected.extend(day(24, 9, 17));
+        // the Saturday window runs past midnight into the clock change
+        expected.push((local(2025, 10, 25, 22, 0, 0), Action::Start, local(2025, 10, 25, 22, 0, 0)));
+        expected.push((local(2025, 10, 26, 2, 0, 0), Action::Stop, local(2025, 10, 25, 22, 0, 0)));
+        expected.extend(day(26, 12, 13));
+        expected.extend(day(27, 9, 17));
+        expected.extend(day(28, 9, 17));
+        expected.extend(day(29, 9, 17));
+        expected.push((local(2025, 10, 30, 9, 0, 0), Action::Start, local(2025, 10, 30, 9, 0, 0)));
+        assert_eq!(raised, expected);
+
+        // the overnight window lasts five hours as the clocks went back an hour
+        assert_eq!(expected[5].0 - expected[4].0, TimeDelta::hours(5));
+    }
+
+    #[test]
+    fn test_simulated_back_to_back_windows() {
+        // 2025-06-02 is a Monday
+        let table = timetable(vec![Weekday::Monday(vec![
+            ("08:00:00".to_string(), "12:00:00".to_string()),
+            ("12:00:00".to_string(), "16:00:00".to_string()),
+        ])]);
+        let clock = SimulatedClock::new(local(2025, 6, 2, 7, 0, 0).with_timezone(&Utc));
+        let raised = simulate(&table, clock, local(2025, 6, 2, 18, 0, 0));
+
+        assert_eq!(raised, vec![
+            (local(2025, 6, 2, 8, 0, 0), Action::Start, local(2025, 6, 2, 8, 0, 0)),
+            (local(2025, 6, 2, 12, 0, 0), Action::Stop, local(2025, 6, 2, 8, 0, 0)),
+            (local(2025, 6, 2, 12, 0, 0), Action::Start, local(2025, 6, 2, 12, 0, 0)),
+            (local(2025, 6, 2, 16, 0, 0), Action::Stop, local(2025, 6, 2, 12, 0, 0)),
+        ]);
+    }
+
+    #[test]
+    fn test_simulated_interrupt_at_window_start() {
+        let mut table = timetable(vec![Weekday::Monday(vec![("08:00:00".to_string(), "12:00:00".to_string())])]);
+        let interrupt = table.add_interrupt(InterruptSchedule {
+            at: InterruptTime::parse("08:00:00").unwrap(),
+            duration: Some(TimeDelta::minutes(1)),
+        });
+        let clock = SimulatedClock::new(local(2025, 6, 2, 7, 0, 0).with_timezone(&Utc));
+        let raised = simulate(&table, clock, local(2025, 6, 2, 13, 0, 0));
+
+        // the interrupt pre-empts the task the window has just started
+        let actions = raised.iter().map(|(at, action, _)| (*at, *action)).collect::<Vec<_>>();
+        assert_eq!(actions, vec![
+            (local(2025, 6, 2, 8, 0, 0), Action::Start),
+            (local(2025, 6, 2, 8, 0, 0), Action::Interrupt(interrupt)),
+            (local(2025, 6, 2, 8, 1, 0), Action::InterruptEnd(interrupt)),
+            (local(2025, 6, 2, 12, 0, 0), Action::Stop),
+        ]);
+    }
+
+    #[test]
+    fn test_simulated_slow_handler() {
+        let mut table = timetable(vec![Weekday::Monday(vec![("08:00:00".to_string(), "12:00:00".to_string())])]);
+        let interrupt = table.add_interrupt(InterruptSchedule {
+            at: InterruptTime::parse("08:00:02").unwrap(),
+            duration: Some(TimeDelta::minutes(1)),
+        });
+        let clock = SimulatedClock::new(local(2025, 6, 2, 7, 0, 0).with_timezone(&Utc));
+        let raised = simulate_slow_start(&table, &clock, local(2025, 6, 2, 13, 0, 0), TimeDelta::seconds(5));
+
+        // the interrupt fell due while the start was being handled, and is still raised
+        let actions = raised.iter().map(|(at, action, _)| (*at, *action)).collect::<Vec<_>>();
+        assert_eq!(actions, vec![
+            (local(2025, 6, 2, 8, 0, 0), Action::Start),
+            (local(2025, 6, 2, 8, 0, 2), Action::Interrupt(interrupt)),
+            (local(2025, 6, 2, 8, 1, 2), Action::InterruptEnd(interrupt)),
+            (local(2025, 6, 2, 12, 0, 0), Action::Stop),
+        ]);
+    }
+
+    #[test]
+    fn test_simulated_jump_while_handling() {
+        let mut table = timetable(vec![Weekday::Monday(vec![("08:00:00".to_string(), "12:00:00".to_string())])]);
+        table.add_interrupt(InterruptSchedule {
+            at: InterruptTime::parse("08:00:02").unwrap(),
+            duration: Some(TimeDelta::minutes(1)),
+        });
+        // NTP moves the clock on while the start is being handled
+        let clock = SimulatedClock::new(local(2025, 6, 2, 7, 0, 0).with_timezone(&Utc));
+        clock.jump_at(local(2025, 6, 2, 8, 0, 3).with_timezone(&Utc), local(2025, 6, 2, 13, 0, 0).with_timezone(&Utc));
+        let raised = simulate_slow_start(&table, &clock, local(2025, 6, 2, 18, 0, 0), TimeDelta::seconds(5));
+
+        // nothing is raised for the skipped time, the task is stopped as the window is over
+        assert_eq!(raised, vec![
+            (local(2025, 6, 2, 8, 0, 0), Action::Start, local(2025, 6, 2, 8, 0, 0)),
+            (local(2025, 6, 2, 13, 0, 2), Action::Stop, local(2025, 6, 2, 13, 0, 2)),
+        ]);
+    }
+
+    #[test]
+    fn test_simulated_clock_corrections() {
+        let table = timetable(office_hours());
+        // a device without an RTC boots thinking it is early morning, then NTP corrects it
+        // to midday, inside the window. Later the clock is wound back before the window.
+        let boot = local(2025, 6, 2, 6, 0, 0);
+        let clock = SimulatedClock::new(boot.with_timezone(&Utc));
+        clock.jump_at(local(2025, 6, 2, 6, 0, 5).with_timezone(&Utc), local(2025, 6, 2, 12, 0, 0).with_timezone(&Utc));
+        clock.jump_at(local(2025, 6, 2, 14, 0, 0).with_timezone(&Utc), local(2025, 6, 2, 8, 0, 0).with_timezone(&Utc));
+        let raised = simulate(&table, clock, local(2025, 6, 2, 18, 0, 0));
+
+        assert_eq!(raised, vec![
+            (local(2025, 6, 2, 12, 0, 0), Action::Start, local(2025, 6, 2, 9, 0, 0)),
+            (local(2025, 6, 2, 8, 0, 0), Action::Stop, local(2025, 6, 2, 8, 0, 0)),
+            (local(2025, 6, 2, 9, 0, 0), Action::Start, local(2025, 6, 2, 9, 0, 0)),
+            (local(2025, 6, 2, 17, 0, 0), Action::Stop, local(2025, 6, 2, 9, 0, 0)),
+        ]);
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    io,
+    process::{
+        self,
+        Child,
+        Command
+    },
+    sync::atomic::{
+        AtomicBool,
+        AtomicU32,
+        Ordering
+    },
+};
+
+use crate::{
+    logd,
+    logw
+};
+use log::{
+    debug,
+    warn
+};
+
+use crate::{
+    Model,
+    Task,
+    launcher::Launcher
+};
+
+/// Set by `MT_SCOPE=true` in the config
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Numbers the scopes so that every unit name is unique
+static NEXT_SCOPE: AtomicU32 = AtomicU32::new(1);
+
+/// Sets whether tasks are run in their own systemd scope
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// The resources a task may use, by model. Chromium in particular can use all of the memory
+/// on an Eco box, so it is killed by the kernel instead of the whole device slowing down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Limits {
+    memory_max: &'static str,
+    cpu_quota: &'static str,
+}
+
+impl Limits {
+    fn for_model(model: &Model) -> Limits {
+        match model {
+            Model::Eco => Limits { memory_max: "384M", cpu_quota: "150%" },
+            Model::Standard => Limits { memory_max: "1G", cpu_quota: "300%" },
+            Model::Pro => Limits { memory_max: "3G", cpu_quota: "400%" },
+        }
+    }
+}
+
+/// The name of a task's scope, without the `.scope` suffix. Our pid keeps the names unique
+/// across restarts of the service.
+fn unit_name(task: &Task, number: u32) -> String {
+    format!("mediatimer-{}-{}-{}", task.proc_type.as_str(), process::id(), number)
+}
+
+/// Launches each command inside a transient systemd user scope with the limits for the task's
+/// model. `systemd-run --scope` runs the player itself, so the pid is still the player's, and
+/// the scope's cgroup holds everything it starts, even processes that leave its process group.
+pub struct Scoped<'a> {
+    launcher: &'a dyn Launcher,
+    pub name: String,
+    limits: Limits,
+}
+
+impl<'a> Scoped<'a> {
+    pub fn new(launcher: &'a dyn Launcher, task: &Task) -> Scoped<'a> {
+        Scoped {
+            launcher,
+            name: unit_name(task, NEXT_SCOPE.fetch_add(1, Ordering::Relaxed)),
+            limits: Limits::for_model(&task.model),
+        }
+    }
+
+    /// Builds the `systemd-run` command that runs `command` in the scope
+    fn wrap(&self, command: &Command) -> Command {
+        let mut scoped = Command::new("systemd-run");
+        scoped.arg("--user")
+            .arg("--scope")
+            .arg("--quiet")
+            .arg("--collect")
+            .arg(format!("--unit={}", self.name))
+            .arg("-p")
+            .arg(format!("MemoryMax={}", self.limits.memory_max))
+            .arg("-p")
+            .arg(format!("CPUQuota={}", self.limits.cpu_quota))
+            .arg("--")
+            .arg(command.get_program())
+            .args(command.get_args());
+        for (key, value) in command.get_envs() {
+            match value {
+                Some(value) => scoped.env(key, value),
+                None => scoped.env_remove(key)
+            };
+        }
+        if let Some(dir) = command.get_current_dir() {
+            scoped.current_dir(dir);
+        }
+        scoped
+    }
+}
+
+impl Launcher for Scoped<'_> {
+    fn spawn(&self, command: &mut Command) -> io::Result<Child> {
+        self.launcher.spawn(&mut self.wrap(command))
+    }
+}
+
+/// Kills anything left in the scope once its task has been stopped, including processes that
+/// left the task's process group. The scope may already have gone with its last process.
+pub fn kill(name: &str) {
+    match Command::new("systemctl")
+        .arg("--user")
+        .arg("kill")
+        .arg("--signal=SIGKILL")
+        .arg(format!("{}.scope", name))
+        .output() {
+        Ok(output) if !output.status.success() => logd!("Scope {} was already stopped", name),
+        Ok(_) => logd!("Killed what was left in scope {}", name),
+        Err(e) => logw!("Failed to clear up scope {}: {}", name, e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Autoloop,
+        ProcType,
+        launcher::mock::{
+            MockLauncher,
+            Outcome
+        }
+    };
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_scoped_command() {
+        let task = Task::new(Model::Eco, ProcType::Web, Autoloop::No, PathBuf::new(), 5, String::from("https://example.com"));
+        let mock = MockLauncher::new(Outcome::Exit(0));
+        let scoped = Scoped::new(&mock, &task);
+        assert!(scoped.name.starts_with(&format!("mediatimer-web-{}-", process::id())));
+
+        let mut child = scoped.spawn(Command::new("chromium").arg("--incognito").arg("https://example.com")).unwrap();
+        child.wait().unwrap();
+        let unit = format!("--unit={}", scoped.name);
+        assert_eq!(mock.launched(), vec![vec![
+            "systemd-run", "--user", "--scope", "--quiet", "--collect", unit.as_str(),
+            "-p", "MemoryMax=384M", "-p", "CPUQuota=150%",
+            "--", "chromium", "--incognito", "https://example.com"
+        ]]);
+
+        // every task gets its own scope
+        assert_ne!(Scoped::new(&mock, &task).name, scoped.name);
+    }
+
+    #[test]
+    fn test_limits() {
+        assert_eq!(Limits::for_model(&Model::Eco).memory_max, "384M");
+        assert_eq!(Limits::for_model(&Model::Standard), Limits { memory_max: "1G", cpu_quota: "300%" });
+        assert_eq!(Limits::for_model(&Model::Pro).cpu_quota, "400%");
+    }
+}
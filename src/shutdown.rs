@@ -0,0 +1,238 @@
+use std::{
+    io,
+    process,
+    sync::Arc,
+    thread,
+    time::{
+        Duration,
+        Instant
+    },
+};
+
+use nix::{
+    errno::Errno,
+    sys::signal::{
+        self,
+        Signal
+    },
+    unistd::Pid,
+};
+use signal_hook::{
+    consts::{
+        SIGHUP,
+        SIGINT,
+        SIGTERM
+    },
+    iterator::Signals
+};
+
+use crate::{
+    logi,
+    logw,
+    loge
+};
+use log::{
+    info,
+    warn,
+    error
+};
+
+use crate::{
+    RunningTask,
+    notify,
+    playback::Playback,
+    scope,
+    proof::{
+        self,
+        EndReason
+    }
+};
+
+/// How long players are given to exit after SIGTERM before they are killed
+pub const TERM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the players are checked while waiting for them to exit
+const EXIT_POLL: Duration = Duration::from_millis(100);
+
+/// How the running tasks were stopped
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Stopped {
+    /// Exited after SIGTERM, or had already exited
+    pub terminated: usize,
+    /// Still running after the timeout, so killed
+    pub killed: usize,
+}
+
+/// Sends the signal to the task's process group, which also reaches anything the player has
+/// started. Every task is launched as the leader of its own group.
+fn signal_task(task: &RunningTask, signal: Signal) -> nix::Result<()> {
+    let pid = Pid::from_raw(task.child.id() as i32);
+    // the task may already have exited
+    match signal::killpg(pid, signal) {
+        Err(Errno::ESRCH) => Ok(()),
+        result => result
+    }
+}
+
+/// Stops every task, first with SIGTERM and then with SIGKILL for any still running after
+/// `timeout`. Each task is reaped, the rest of its process group is killed, and plays are
+/// recorded to the proof of play log.
+pub fn stop_tasks(tasks: Vec<RunningTask>, timeout: Duration) -> Stopped {
+    let mut stopped = Stopped::default();
+    let mut running = Vec::with_capacity(tasks.len());
+    for mut task in tasks {
+        if let Some(play) = task.play.take() {
+            logi!(MT_EVENT = "stop", MT_TASK = play.task(), MT_PID = task.child.id(); "Stopping {} with pid {}", play.task(), task.child.id());
+            let reason = match task.child.try_wait() {
+                Ok(Some(status)) => EndReason::from_status(status),
+                _ => EndReason::Stopped
+            };
+            proof::record(play, reason);
+        }
+        if let Err(e) = signal_task(&task, Signal::SIGTERM) {
+            logw!("Failed to send SIGTERM to {}: {}", task.child.id(), e);
+        }
+        running.push(task);
+    }
+
+    let deadline = Instant::now() + timeout;
+    while !running.is_empty() {
+        let mut still_running = Vec::with_capacity(running.len());
+        for mut task in running {
+            match task.child.try_wait() {
+                Ok(Some(_)) | Err(_) => {
+                    stopped.terminated += 1;
+                    finish(&task);
+                },
+                Ok(None) if Instant::now() >= deadline => {
+                    logw!("Task {} did not exit after SIGTERM, killing it", task.child.id());
+                    if let Err(e) = signal_task(&task, Signal::SIGKILL) {
+                        loge!("Failed to kill task {}: {}", task.child.id(), e);
+                    }
+                    let _ = task.child.wait();
+                    stopped.killed += 1;
+                    finish(&task);
+                },
+                Ok(None) => still_running.push(task)
+            }
+        }
+        running = still_running;
+        if !running.is_empty() {
+            thread::sleep(EXIT_POLL);
+        }
+    }
+    stopped
+}
+
+/// Kills anything left in the task's process group, and in its systemd scope if it has one,
+/// once the task itself has exited
+fn finish(task: &RunningTask) {
+    let pid = Pid::from_raw(task.child.id() as i32);
+    match signal::killpg(pid, Signal::SIGKILL) {
+        Ok(()) | Err(Errno::ESRCH) | Err(Errno::EPERM) => {},
+        Err(e) => logw!("Failed to clear up process group {}: {}", pid, e)
+    }
+    if let Some(name) = &task.scope {
+        scope::kill(name);
+    }
+}
+
+/// Stops every running task and exits when the service is asked to stop. Nothing new can be
+/// started once the players are being stopped.
+pub fn handle_signals(playback: Arc<Playback>) -> io::Result<()> {
+    let mut signals = Signals::new([SIGTERM, SIGINT, SIGHUP])?;
+    thread::spawn(move || {
+        if let Some(received) = signals.forever().next() {
+            logi!(MT_EVENT = "shutdown"; "Received signal {}, stopping all tasks", received);
+            notify::stopping();
+            playback.stop_all_then(|stopped| {
+                logi!("Stopped {} tasks, {} of them had to be killed", stopped.terminated + stopped.killed, stopped.killed);
+                // the last plays are still being recorded
+                proof::flush();
+                process::exit(0)
+            })
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Autoloop,
+        Model,
+        ProcType,
+        Task,
+        loggers,
+        proof::Play
+    };
+    use std::{
+        fs,
+        io::{
+            BufRead,
+            BufReader
+        },
+        os::unix::process::CommandExt,
+        path::PathBuf,
+        process::{
+            Command,
+            Stdio
+        }
+    };
+
+    /// True while the process exists and is not a zombie waiting to be reaped
+    fn is_running(pid: u32) -> bool {
+        fs::read_to_string(format!("/proc/{}/stat", pid))
+            .is_ok_and(|stat| stat.rsplit(')').next().is_some_and(|rest| !rest.trim_start().starts_with('Z')))
+    }
+
+    #[test]
+    fn test_stop_tasks() {
+        let player = Command::new("sleep").arg("30").process_group(0).spawn().unwrap();
+        let stubborn = Command::new("sh").arg("-c").arg("trap '' TERM; sleep 30 & wait").process_group(0).spawn().unwrap();
+        // an executable that has started something else in its process group
+        let mut executable = Command::new("sh")
+            .arg("-c")
+            .arg("sleep 30 & echo $!; wait")
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(executable.stdout.take().unwrap()).read_line(&mut line).unwrap();
+        let grandchild: u32 = line.trim().parse().unwrap();
+        assert!(is_running(grandchild));
+
+        let pids = [player.id(), stubborn.id(), executable.id()];
+        let tasks = vec![
+            RunningTask::new(player),
+            RunningTask::new(stubborn),
+            RunningTask::new(executable),
+        ];
+        let stopped = stop_tasks(tasks, Duration::from_millis(500));
+        assert_eq!(stopped, Stopped { terminated: 2, killed: 1 });
+
+        for pid in pids {
+            assert!(!is_running(pid));
+        }
+        // the rest of the group is stopped, it may take a moment to be reaped
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while is_running(grandchild) && Instant::now() < deadline {
+            thread::sleep(EXIT_POLL);
+        }
+        assert!(!is_running(grandchild));
+    }
+
+    #[test]
+    fn test_stop_is_logged() {
+        loggers::capture::start();
+        let task = Task::new(Model::Pro, ProcType::Video, Autoloop::No, PathBuf::from("/media/film.mp4"), 5, String::new());
+        let player = Command::new("sleep").arg("30").process_group(0).spawn().unwrap();
+        let pid = player.id();
+        stop_tasks(vec![RunningTask::new(player).with_play(Play::begin(&task))], TERM_TIMEOUT);
+
+        let event = format!("MT_EVENT=stop MT_TASK=/media/film.mp4 MT_PID={}\n", pid);
+        assert!(loggers::capture::lines().iter().any(|line| line.ends_with(&event)));
+    }
+}
@@ -0,0 +1,101 @@
+use chrono::{
+    DateTime,
+    NaiveDate,
+    Utc
+};
+
+/// A position on the earth in decimal degrees, north and east positive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SolarEvent {
+    Sunrise,
+    Sunset
+}
+
+/// Julian date of 2000-01-01 12:00 UTC
+const J2000: f64 = 2451545.0;
+
+/// Julian date of the unix epoch
+const UNIX_EPOCH_JD: f64 = 2440587.5;
+
+/// Axial tilt of the earth in degrees
+const OBLIQUITY: f64 = 23.4397;
+
+/// Solar altitude at sunrise and sunset, allowing for refraction and the size of the sun
+const HORIZON: f64 = -0.833;
+
+/// Calculates the time of sunrise or sunset on a date using the sunrise equation. This is
+/// accurate to within a couple of minutes, which is plenty for switching a screen on at
+/// dusk, and needs no network access.
+/// Returns None when the sun does not rise or set on that date (polar day or night).
+pub fn event_time(date: NaiveDate, location: Location, event: SolarEvent) -> Option<DateTime<Utc>> {
+    let epoch = NaiveDate::from_ymd_opt(2000, 1, 1)?;
+    let days = (date - epoch).num_days() as f64;
+
+    // mean solar time at the location
+    let mean_solar = days - location.longitude / 360.0;
+    let anomaly = (357.5291 + 0.98560028 * mean_solar).rem_euclid(360.0);
+    let m = anomaly.to_radians();
+    let centre = 1.9148 * m.sin() + 0.0200 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic_longitude = (anomaly + centre + 180.0 + 102.9372).rem_euclid(360.0).to_radians();
+    let transit = J2000 + mean_solar + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+    let declination = (ecliptic_longitude.sin() * OBLIQUITY.to_radians().sin()).asin();
+    let latitude = location.latitude.to_radians();
+    let cos_hour_angle = (HORIZON.to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+    let hour_angle = cos_hour_angle.acos().to_degrees() / 360.0;
+
+    let julian = match event {
+        SolarEvent::Sunrise => transit - hour_angle,
+        SolarEvent::Sunset => transit + hour_angle
+    };
+    let millis = ((julian - UNIX_EPOCH_JD) * 86_400_000.0).round() as i64;
+    DateTime::from_timestamp_millis(millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const LONDON: Location = Location { latitude: 51.5074, longitude: -0.1278 };
+
+    fn assert_close(actual: DateTime<Utc>, expected: DateTime<Utc>) {
+        let diff = (actual - expected).num_seconds().abs();
+        assert!(diff < 180, "expected {} but calculated {}", expected, actual);
+    }
+
+    #[test]
+    fn test_london_midsummer() {
+        let date = NaiveDate::from_ymd_opt(2025, 6, 21).unwrap();
+        let sunrise = event_time(date, LONDON, SolarEvent::Sunrise).unwrap();
+        let sunset = event_time(date, LONDON, SolarEvent::Sunset).unwrap();
+        assert_close(sunrise, Utc.with_ymd_and_hms(2025, 6, 21, 3, 43, 0).unwrap());
+        assert_close(sunset, Utc.with_ymd_and_hms(2025, 6, 21, 20, 21, 0).unwrap());
+    }
+
+    #[test]
+    fn test_london_midwinter() {
+        let date = NaiveDate::from_ymd_opt(2025, 12, 21).unwrap();
+        let sunrise = event_time(date, LONDON, SolarEvent::Sunrise).unwrap();
+        let sunset = event_time(date, LONDON, SolarEvent::Sunset).unwrap();
+        assert_close(sunrise, Utc.with_ymd_and_hms(2025, 12, 21, 8, 4, 0).unwrap());
+        assert_close(sunset, Utc.with_ymd_and_hms(2025, 12, 21, 15, 53, 0).unwrap());
+    }
+
+    #[test]
+    fn test_polar_night() {
+        let tromso = Location { latitude: 69.6492, longitude: 18.9553 };
+        let date = NaiveDate::from_ymd_opt(2025, 12, 21).unwrap();
+        assert_eq!(event_time(date, tromso, SolarEvent::Sunrise), None);
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf
+    },
+    time::Duration,
+};
+
+use chrono::TimeDelta;
+
+use crate::logw;
+use log::warn;
+
+use crate::{
+    error::MediatimerError,
+    scheduler::ScheduledEvent,
+    timezone
+};
+
+fn status_path() -> PathBuf {
+    let username = whoami::username();
+    ["/home/", &username, ".mediatimer_config/status"].iter().collect()
+}
+
+/// The `MT_SCOPE` value already in the status contents
+fn current_scope(contents: &str) -> Option<&str> {
+    contents.lines()
+        .find_map(|line| line.strip_prefix("MT_SCOPE="))
+        .and_then(|scope| scope.strip_suffix(".scope"))
+}
+
+/// Replaces the `MT_SCOPE` line of the status contents with the systemd scope of the task now
+/// playing, or removes it if the task has no scope
+fn with_scope(contents: &str, scope: Option<&str>) -> String {
+    let mut updated = contents.lines()
+        .filter(|line| !line.starts_with("MT_SCOPE="))
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+    if let Some(scope) = scope {
+        updated.push_str(&format!("MT_SCOPE={}.scope\n", scope));
+    }
+    updated
+}
+
+fn upcoming_to(path: &Path, events: &[ScheduledEvent]) -> io::Result<()> {
+    let previous = fs::read_to_string(path).unwrap_or_default();
+    let mut contents = format!("MT_UPDATED={}\n", timezone::now().format("%Y-%m-%d %H:%M:%S %z"));
+    for event in events.iter() {
+        contents.push_str(&format!("MT_NEXT={}\n", event));
+    }
+    fs::write(path, with_scope(&contents, current_scope(&previous)))
+}
+
+fn scope_to(path: &Path, scope: Option<&str>) -> io::Result<()> {
+    let contents = fs::read_to_string(path).unwrap_or_default();
+    fs::write(path, with_scope(&contents, scope))
+}
+
+/// Writes the upcoming scheduled events to the status file so that other tools can report
+/// what the device is about to do. Each event is written as an `MT_NEXT` line, and the scope
+/// of the task playing is kept.
+pub fn write_upcoming(events: &[ScheduledEvent]) {
+    if let Err(e) = upcoming_to(&status_path(), events) {
+        logw!("Failed to write status file: {}", e);
+    }
+}
+
+/// Writes the systemd scope of the task now playing to the status file, keeping the rest
+pub fn write_scope(scope: Option<&str>) {
+    if let Err(e) = scope_to(&status_path(), scope) {
+        logw!("Failed to write status file: {}", e);
+    }
+}
+
+/// Writes the error the device is stuck on to the status file, with the time start up will
+/// next be tried. The error is cleared by the next `write_upcoming`.
+pub fn write_error(error: &MediatimerError, retry_in: Duration) {
+    let now = timezone::now();
+    let retry_at = now + TimeDelta::from_std(retry_in).unwrap_or(TimeDelta::zero());
+    let contents = format!(
+        "MT_UPDATED={}\nMT_ERROR={}\nMT_ERROR_MESSAGE={}\nMT_RETRY={}\n",
+        now.format("%Y-%m-%d %H:%M:%S %z"),
+        error.code(),
+        error.message().replace('\n', " "),
+        retry_at.format("%Y-%m-%d %H:%M:%S %z")
+    );
+    if let Err(e) = fs::write(status_path(), contents) {
+        logw!("Failed to write status file: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_scope() {
+        let contents = "MT_UPDATED=2026-10-18 09:00:00 +0100\nMT_NEXT=start at 10:00\n";
+        let scoped = with_scope(contents, Some("mediatimer-video-812-1"));
+        assert_eq!(scoped, format!("{}MT_SCOPE=mediatimer-video-812-1.scope\n", contents));
+        // the next task replaces the scope
+        assert_eq!(with_scope(&scoped, Some("mediatimer-web-812-2")), format!("{}MT_SCOPE=mediatimer-web-812-2.scope\n", contents));
+        assert_eq!(with_scope(&scoped, None), contents);
+        assert_eq!(with_scope("", None), "");
+    }
+
+    #[test]
+    fn test_upcoming_keeps_scope() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("status");
+        upcoming_to(&path, &[]).unwrap();
+        scope_to(&path, Some("mediatimer-video-812-1")).unwrap();
+        // the scheduler writes the upcoming events again straight after a task starts
+        upcoming_to(&path, &[]).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with("MT_UPDATED="));
+        assert_eq!(current_scope(&contents), Some("mediatimer-video-812-1"));
+        assert_eq!(contents.matches("MT_SCOPE=").count(), 1);
+
+        scope_to(&path, None).unwrap();
+        upcoming_to(&path, &[]).unwrap();
+        assert_eq!(current_scope(&fs::read_to_string(&path).unwrap()), None);
+    }
+}
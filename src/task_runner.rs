@@ -1,22 +1,19 @@
 use std::{
-    error::Error,
-    sync::{Arc, Mutex},
-    thread,
+    io,
+    sync::Mutex,
     process::{
+        Child,
         Command
     },
-    os::unix::process::CommandExt,
 
 };
 
 use crate::{
     logi,
-    loge,
-    logw
+    loge
 };
 use log::{
     info,
-    warn,
     error
 };
 
@@ -26,414 +23,403 @@ use crate::{
     Autoloop,
     ProcType,
     Model,
-    stop_task
-};
-
-use chrono::{
-    Local,
-    DateTime
+    error::MediatimerError,
+    launcher::Launcher,
+    notify,
+    playback::Playback,
+    scope::{
+        self,
+        Scoped
+    },
+    timezone
 };
 
+use chrono::DateTime;
+use chrono_tz::Tz;
 
 
-fn get_seek_seconds(start_time: &str) -> Result<String, Box<dyn Error>> {
-    let local = Local::now();
-    let now_timestamp = local.timestamp_millis();
-
-    let date_string = format!("{}", local.format("%Y:%m:%d:%H:%M:%S:%z"));
 
-    let date_nums: Vec<&str> = date_string.split(":").collect::<Vec<&str>>();
-    let year_num = date_nums[0];
-    let month_num = date_nums[1];
-    let day_num = date_nums[2];
-    let _hour_num = date_nums[3];
-    let _min_num = date_nums[4];
-    let _sec_num = date_nums[5];
-    let timezone = date_nums[6];
-    let formatted_date = format!("{}:{}:{}:{} {}", year_num, month_num, day_num, start_time, timezone);
-    let start_dt = DateTime::parse_from_str(&formatted_date, "%Y:%m:%d:%H:%M:%S %z")?;
-
-    let start_timestamp = start_dt.timestamp_millis();
-
-    if now_timestamp > start_timestamp {
-        let mut time_diff = (now_timestamp - start_timestamp).to_string();
-        time_diff.push_str("ms");
+/// Returns how far into the window the task is being started, as an ffplay duration.
+/// Both times are absolute instants, so the result is correct across midnight and daylight
+/// saving changes.
+fn get_seek_seconds(window_start: DateTime<Tz>, now: DateTime<Tz>) -> String {
+    let time_diff = (now - window_start).num_milliseconds();
+    if time_diff > 0 {
+        let time_diff = format!("{}ms", time_diff);
         logi!("Time Difference: {}", time_diff);
-        Ok(time_diff)
+        time_diff
     } else {
-        Ok(String::from("0"))
+        String::from("0")
     }
 }
 
-/// This function takes the task to run and launches the correct software based on the variables 
-/// set within the Task struct
-pub fn run_task(task_list: Arc<Mutex<Vec<RunningTask>>>, task: Arc<Mutex<Task>>, start_time: &str) -> Result<(), Box<dyn Error>> {
-    let task_list_clone = Arc::clone(&task_list);
-    let task_list_clone_two = Arc::clone(&task_list);
-
-    logi!("Run task: {:?}", task.lock().unwrap());
-    
-    let model = task.lock().unwrap().model.clone();
+/// Launches the correct software for a task, in its own systemd scope if `MT_SCOPE` is set
+pub fn spawn_child(launcher: &dyn Launcher, task: &Task, seek_seconds: &str) -> io::Result<RunningTask> {
+    if scope::enabled() {
+        let scoped = Scoped::new(launcher, task);
+        let child = spawn_player(&scoped, task, seek_seconds)?;
+        Ok(RunningTask::new(child).with_scope(scoped.name))
+    } else {
+        Ok(RunningTask::new(spawn_player(launcher, task, seek_seconds)?))
+    }
+}
 
-    let looper = task.lock().unwrap().auto_loop.clone();
+/// Launches the correct software for a task based on the variables set within the Task
+/// struct, seeking media that is not looped to `seek_seconds`.
+fn spawn_player(launcher: &dyn Launcher, task: &Task, seek_seconds: &str) -> io::Result<Child> {
+    let model = task.model.clone();
 
-    let file_binding = task.lock().unwrap().file.clone();
-    let file = String::from(file_binding.to_str().unwrap());
-    let web_url = task.lock().unwrap().web_url.clone();
-    let slide_delay = task.lock().unwrap().slide_delay.to_string();
+    let looper = task.auto_loop;
 
-    // get seek seconds
-    let mut seek_seconds: String = String::new();
-    if start_time != "" {
-        seek_seconds = get_seek_seconds(start_time)?;
-    }
+    let file = task.file.to_string_lossy().to_string();
+    let web_url = task.web_url.clone();
+    let slide_delay = task.slide_delay.to_string();
 
     if model == Model::Eco {
-        match task.lock().unwrap().proc_type {
+        match task.proc_type {
             ProcType::Video => {
                 match looper {
                     Autoloop::Yes => {
-                        thread::spawn(move || {
-                            let child = Command::new("ffplay")
-                                .arg("-hide_banner")
-                                .arg("-loglevel")
-                                .arg("error")
-                                .arg("-an")
-                                .arg("-fs")
-                                .arg("-loop")
-                                .arg("-1")
-                                .arg(&file)
-                                .spawn().expect("no child");
-
-                            let running_task = RunningTask::new(child, false );
-                            task_list_clone.lock().unwrap().push(running_task);
-                        });
-
+                        launcher.spawn(Command::new("ffplay")
+                            .arg("-hide_banner")
+                            .arg("-loglevel")
+                            .arg("error")
+                            .arg("-an")
+                            .arg("-fs")
+                            .arg("-loop")
+                            .arg("-1")
+                            .arg(&file))
                     }
                     Autoloop::No => {
-                        thread::spawn(move || {
-                            let child = Command::new("ffplay")
-                                .arg("-hide_banner")
-                                .arg("-loglevel")
-                                .arg("error")
-                                .arg("-an")
-                                .arg("-fs")
-                                .arg("-ss")
-                                .arg(seek_seconds)
-                                .arg(&file)
-                                .spawn().expect("no child");
-
-                            let running_task = RunningTask::new(child, false);
-                            task_list_clone.lock().unwrap().push(running_task);
-                        });
+                        launcher.spawn(Command::new("ffplay")
+                            .arg("-hide_banner")
+                            .arg("-loglevel")
+                            .arg("error")
+                            .arg("-an")
+                            .arg("-fs")
+                            .arg("-ss")
+                            .arg(seek_seconds)
+                            .arg(&file))
                     }
-                };
+                }
             },
             ProcType::Audio => {
                 match looper {
                     Autoloop::Yes => {
-                        thread::spawn(move || {
-                            let child = Command::new("ffplay")
-                                .arg("-hide_banner")
-                                .arg("-loglevel")
-                                .arg("error")
-                                //.arg("-nodisp")
-                                //.arg("-fs")
-                                .arg("-loop")
-                                .arg("-1")
-                                .arg(&file)
-                                .spawn().expect("no child");
-
-                            let running_task = RunningTask::new(child, false);
-                            task_list_clone.lock().unwrap().push(running_task);
-                        });
-
+                        launcher.spawn(Command::new("ffplay")
+                            .arg("-hide_banner")
+                            .arg("-loglevel")
+                            .arg("error")
+                            //.arg("-nodisp")
+                            //.arg("-fs")
+                            .arg("-loop")
+                            .arg("-1")
+                            .arg(&file))
                     }
                     Autoloop::No => {
-                        thread::spawn(move || {
-                            let child = Command::new("ffplay")
-                                .arg("-hide_banner")
-                                .arg("-loglevel")
-                                .arg("error")
-                                //.arg("-fs")
-                                //.arg("-nodisp")
-                                .arg("-ss")
-                                .arg(seek_seconds)
-                                .arg(&file)
-                                .spawn().expect("no child");
-
-                            let running_task = RunningTask::new(child, false);
-                            task_list_clone.lock().unwrap().push(running_task);
-                        });
+                        launcher.spawn(Command::new("ffplay")
+                            .arg("-hide_banner")
+                            .arg("-loglevel")
+                            .arg("error")
+                            //.arg("-fs")
+                            //.arg("-nodisp")
+                            .arg("-ss")
+                            .arg(seek_seconds)
+                            .arg(&file))
                     }
-                };
+                }
             },
             ProcType::Image => {
-                thread::spawn(move || {
-                    let child = Command::new("feh")
-                        .arg("-YxqFZz")
-                        .arg("-B")
-                        .arg("black")
-                        .arg(&file)
-                        .spawn().expect("no child");
-
-                    let running_task = RunningTask::new(child, false);
-                    task_list_clone.lock().unwrap().push(running_task);
-                });
+                launcher.spawn(Command::new("feh")
+                    .arg("-YxqFZz")
+                    .arg("-B")
+                    .arg("black")
+                    .arg(&file))
             },
             ProcType::Slideshow => {
-                thread::spawn(move || {
-                    let child = Command::new("feh")
-                        .arg("-YxqFZz")
-                        .arg("-B")
-                        .arg("black")
-                        .arg("-D")
-                        .arg(&slide_delay)
-                        .arg(&file)
-                        .spawn().expect("no child");
-                    let running_task = RunningTask::new(child, false);
-                    task_list_clone.lock().unwrap().push(running_task);
-                });
+                launcher.spawn(Command::new("feh")
+                    .arg("-YxqFZz")
+                    .arg("-B")
+                    .arg("black")
+                    .arg("-D")
+                    .arg(&slide_delay)
+                    .arg(&file))
             },
             ProcType::Web => {
-                thread::spawn(move || {
-                    let child = Command::new("chromium")
-                        //.arg("--user-data-dir=/tmp/chromium/")
-                        //.arg("--disable-session-crashed-bubble")
-                        .arg("--disable-infobars")
-                        //.arg("--kiosk")
-                        .arg("--incognito")
-                        .arg("--start-fullscreen")
-                        .arg("--start-maximized")
-                        .arg(&web_url)
-                        .spawn().expect("no child");
-
-                    let running_task = RunningTask::new(child, false);
-                    task_list_clone.lock().unwrap().push(running_task);
-                });
-
+                launcher.spawn(Command::new("chromium")
+                    //.arg("--user-data-dir=/tmp/chromium/")
+                    //.arg("--disable-session-crashed-bubble")
+                    .arg("--disable-infobars")
+                    //.arg("--kiosk")
+                    .arg("--incognito")
+                    .arg("--start-fullscreen")
+                    .arg("--start-maximized")
+                    .arg(&web_url))
             },
 
             ProcType::Browser => {
-                thread::spawn(move || {
-                    let child = Command::new("chromium")
-                        //.arg("--user-data-dir=/tmp/chromium/")
-                        //.arg("--disable-session-crashed-bubble")
-                        .arg("--disable-infobars")
-                        //.arg("--kiosk")
-                        .arg("--incognito")
-                        .arg("--start-fullscreen")
-                        .arg("--start-maximized")
-                        .arg(&file)
-                        .spawn().expect("no child");
-
-                    let running_task = RunningTask::new(child, false);
-                    task_list_clone.lock().unwrap().push(running_task);
-                });
-
+                launcher.spawn(Command::new("chromium")
+                    //.arg("--user-data-dir=/tmp/chromium/")
+                    //.arg("--disable-session-crashed-bubble")
+                    .arg("--disable-infobars")
+                    //.arg("--kiosk")
+                    .arg("--incognito")
+                    .arg("--start-fullscreen")
+                    .arg("--start-maximized")
+                    .arg(&file))
             },
             ProcType::Executable => {
-                thread::spawn(move || {
-                    let child = Command::new("sh")
-                        .arg(&file)
-                        .process_group(0)
-                        .spawn().expect("no child");
-
-                    let running_task = RunningTask::new(child, false);
-                    task_list_clone.lock().unwrap().push(running_task);
-                });
-
+                launcher.spawn(Command::new("sh")
+                    .arg(&file))
             }
         }
 
     } else {
         // standard and pro features are identical for mediatimer_init
-        match task.lock().unwrap().proc_type {
+        match task.proc_type {
             ProcType::Video => {
                 match looper {
                     Autoloop::Yes => {
-                        thread::spawn(move || {
-                            let child = Command::new("ffplay")
-                                .arg("-hide_banner")
-                                .arg("-loglevel")
-                                .arg("error")
-                                .arg("-fs")
-                                .arg("-loop")
-                                .arg("-1")
-                                .arg(&file)
-                                .spawn().expect("no child");
-
-                            let running_task = RunningTask::new(child, false);
-                            task_list_clone.lock().unwrap().push(running_task);
-                        });
-
+                        launcher.spawn(Command::new("ffplay")
+                            .arg("-hide_banner")
+                            .arg("-loglevel")
+                            .arg("error")
+                            .arg("-fs")
+                            .arg("-loop")
+                            .arg("-1")
+                            .arg(&file))
                     }
                     Autoloop::No => {
-                        thread::spawn(move || {
-                            let child = Command::new("ffplay")
-                                .arg("-hide_banner")
-                                .arg("-loglevel")
-                                .arg("error")
-                                .arg("-fs")
-                                .arg("-ss")
-                                .arg(seek_seconds)
-                                .arg(&file)
-                                .spawn().expect("no child");
-
-                            let running_task = RunningTask::new(child, false);
-                            task_list_clone.lock().unwrap().push(running_task);
-                        });
+                        launcher.spawn(Command::new("ffplay")
+                            .arg("-hide_banner")
+                            .arg("-loglevel")
+                            .arg("error")
+                            .arg("-fs")
+                            .arg("-ss")
+                            .arg(seek_seconds)
+                            .arg(&file))
                     }
-                };
+                }
             },
             ProcType::Audio => {
                 match looper {
                     Autoloop::Yes => {
-                        thread::spawn(move || {
-                            let child = Command::new("ffplay")
-                                .arg("-hide_banner")
-                                .arg("-loglevel")
-                                .arg("error")
-                                //.arg("-nodisp")
-                                //.arg("-fs")
-                                .arg("-loop")
-                                .arg("-1")
-                                .arg(&file)
-                                .spawn().expect("no child");
-
-                            let running_task = RunningTask::new(child, false);
-                            task_list_clone.lock().unwrap().push(running_task);
-                        });
-
+                        launcher.spawn(Command::new("ffplay")
+                            .arg("-hide_banner")
+                            .arg("-loglevel")
+                            .arg("error")
+                            //.arg("-nodisp")
+                            //.arg("-fs")
+                            .arg("-loop")
+                            .arg("-1")
+                            .arg(&file))
                     }
                     Autoloop::No => {
-                        thread::spawn(move || {
-                            let child = Command::new("ffplay")
-                                .arg("-hide_banner")
-                                .arg("-loglevel")
-                                .arg("error")
-                                //.arg("-fs")
-                                //.arg("-nodisp")
-                                .arg("-ss")
-                                .arg(seek_seconds)
-                                .arg(&file)
-                                .spawn().expect("no child");
-
-                            let running_task = RunningTask::new(child, false);
-                            task_list_clone.lock().unwrap().push(running_task);
-                        });
+                        launcher.spawn(Command::new("ffplay")
+                            .arg("-hide_banner")
+                            .arg("-loglevel")
+                            .arg("error")
+                            //.arg("-fs")
+                            //.arg("-nodisp")
+                            .arg("-ss")
+                            .arg(seek_seconds)
+                            .arg(&file))
                     }
-                };
+                }
             },
             ProcType::Image => {
-                thread::spawn(move || {
-                    let child = Command::new("feh")
-                        .arg("-YxqFZz")
-                        .arg("-B")
-                        .arg("black")
-                        .arg(&file)
-                        .spawn().expect("no child");
-                    let running_task = RunningTask::new(child, false);
-                    task_list_clone.lock().unwrap().push(running_task);
-                });
+                launcher.spawn(Command::new("feh")
+                    .arg("-YxqFZz")
+                    .arg("-B")
+                    .arg("black")
+                    .arg(&file))
             },
             ProcType::Slideshow => {
-                thread::spawn(move || {
-                    let child = Command::new("feh")
-                        .arg("-YxqFZz")
-                        .arg("-B")
-                        .arg("black")
-                        .arg("-D")
-                        .arg(&slide_delay)
-                        .arg(&file)
-                        .spawn().expect("no child");
-                    let running_task = RunningTask::new(child, false);
-                    task_list_clone.lock().unwrap().push(running_task);
-                });
+                launcher.spawn(Command::new("feh")
+                    .arg("-YxqFZz")
+                    .arg("-B")
+                    .arg("black")
+                    .arg("-D")
+                    .arg(&slide_delay)
+                    .arg(&file))
             },
             ProcType::Web => {
-                thread::spawn(move || {
-                    let child = Command::new("chromium")
-                        //.arg("--user-data-dir=/tmp/chromium/")
-                        //.arg("--disable-session-crashed-bubble")
-                        .arg("--disable-infobars")
-                        //.arg("--kiosk")
-                        .arg("--incognito")
-                        .arg("--start-fullscreen")
-                        .arg("--start-maximized")
-                        .arg(&web_url)
-                        .spawn().expect("no child");
-
-                    let running_task = RunningTask::new(child, false);
-                    task_list_clone.lock().unwrap().push(running_task);
-                });
-
+                launcher.spawn(Command::new("chromium")
+                    //.arg("--user-data-dir=/tmp/chromium/")
+                    //.arg("--disable-session-crashed-bubble")
+                    .arg("--disable-infobars")
+                    //.arg("--kiosk")
+                    .arg("--incognito")
+                    .arg("--start-fullscreen")
+                    .arg("--start-maximized")
+                    .arg(&web_url))
             },
 
 
             ProcType::Browser => {
-                thread::spawn(move || {
-                    let child = Command::new("chromium")
-                        //.arg("--user-data-dir=/tmp/chromium/")
-                        //.arg("--disable-session-crashed-bubble")
-                        .arg("--disable-infobars")
-                        //.arg("--kiosk")
-                        .arg("--incognito")
-                        .arg("--start-fullscreen")
-                        .arg("--start-maximized")
-                        .arg(&file)
-                        .spawn().expect("no child");
-
-                    let running_task = RunningTask::new(child, false);
-                    task_list_clone.lock().unwrap().push(running_task);
-                });
-
+                launcher.spawn(Command::new("chromium")
+                    //.arg("--user-data-dir=/tmp/chromium/")
+                    //.arg("--disable-session-crashed-bubble")
+                    .arg("--disable-infobars")
+                    //.arg("--kiosk")
+                    .arg("--incognito")
+                    .arg("--start-fullscreen")
+                    .arg("--start-maximized")
+                    .arg(&file))
             },
             ProcType::Executable => {
-                thread::spawn(move || {
-                    let child = Command::new("sh")
-                        .arg(&file)
-                        .process_group(0)
-                        .spawn().expect("no child");
-
-                    let running_task = RunningTask::new(child, false);
-                    task_list_clone.lock().unwrap().push(running_task);
-                });
-
+                launcher.spawn(Command::new("sh")
+                    .arg(&file))
             }
         }
 
 
     }
-    // stop the task after launching the new task to ensure a smooh overlap
-    logi!("Attempting to stop previous task");
-    let _stopped_task = stop_task(task_list_clone_two.clone());
-    Ok(())
+}
+
+/// This function takes the task to run and launches the correct software based on the variables 
+/// set within the Task struct. When `window_start` is given the media is seeked to the point it
+/// would have reached had it started on time. Whatever was playing is stopped once the task
+/// has started, so the two overlap.
+pub fn run_task(playback: &Playback, task: &Mutex<Task>, window_start: Option<DateTime<Tz>>) -> Result<(), MediatimerError> {
+    let task = task.lock().unwrap();
+    logi!("Run task: {:?}", task);
+
+    // get seek seconds
+    let seek_seconds = match window_start {
+        Some(window_start) => get_seek_seconds(window_start, timezone::now()),
+        None => String::from("0")
+    };
 
+    match playback.play(&task, &seek_seconds) {
+        Ok(started) => {
+            logi!(MT_EVENT = "start", MT_TASK = task.source(), MT_PROCTYPE = task.proc_type.as_str(), MT_PID = started.pid;
+                "Started {} {} with pid {}", task.proc_type.as_str(), task.source(), started.pid);
+            notify::playing(&task, started.scope.as_deref());
+            Ok(())
+        },
+        Err(e) => {
+            loge!(MT_EVENT = "start_failed", MT_TASK = task.source(), MT_PROCTYPE = task.proc_type.as_str();
+                "Failed to launch task: {}", e);
+            Err(e)
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::launcher::mock::{
+        MockLauncher,
+        Outcome
+    };
     use chrono::{
-        Local,
-        TimeDelta
+        TimeDelta,
+        TimeZone
     };
+    use chrono_tz::Tz::Europe__London as London;
+    use std::{
+        os::unix::process::ExitStatusExt,
+        path::PathBuf,
+        process::ExitStatus,
+        sync::Arc
+    };
+
+    const MODELS: [Model; 3] = [Model::Eco, Model::Standard, Model::Pro];
+    const PROC_TYPES: [ProcType; 7] = [
+        ProcType::Video,
+        ProcType::Audio,
+        ProcType::Image,
+        ProcType::Slideshow,
+        ProcType::Web,
+        ProcType::Browser,
+        ProcType::Executable
+    ];
+
+    /// The command line each combination should launch
+    fn expected_command(model: &Model, proc_type: ProcType, auto_loop: Autoloop) -> Vec<&'static str> {
+        let ffplay = ["ffplay", "-hide_banner", "-loglevel", "error"];
+        let chromium = ["chromium", "--disable-infobars", "--incognito", "--start-fullscreen", "--start-maximized"];
+        let position: &[&str] = match auto_loop {
+            Autoloop::Yes => &["-loop", "-1"],
+            Autoloop::No => &["-ss", "1500ms"]
+        };
+        match proc_type {
+            ProcType::Video => {
+                // eco devices play video without sound
+                let screen: &[&str] = if *model == Model::Eco { &["-an", "-fs"] } else { &["-fs"] };
+                [&ffplay[..], screen, position, &["/media/film.mp4"]].concat()
+            },
+            ProcType::Audio => [&ffplay[..], position, &["/media/film.mp4"]].concat(),
+            ProcType::Image => vec!["feh", "-YxqFZz", "-B", "black", "/media/film.mp4"],
+            ProcType::Slideshow => vec!["feh", "-YxqFZz", "-B", "black", "-D", "8", "/media/film.mp4"],
+            ProcType::Web => [&chromium[..], &["https://example.com"]].concat(),
+            ProcType::Browser => [&chromium[..], &["/media/film.mp4"]].concat(),
+            ProcType::Executable => vec!["sh", "/media/film.mp4"]
+        }
+    }
+
+    #[test]
+    fn test_spawn_child_matrix() {
+        for model in MODELS.iter() {
+            for proc_type in PROC_TYPES {
+                for auto_loop in [Autoloop::Yes, Autoloop::No] {
+                    let launcher = MockLauncher::new(Outcome::Exit(0));
+                    let task = Task::new(model.clone(), proc_type, auto_loop, PathBuf::from("/media/film.mp4"), 8, String::from("https://example.com"));
+                    let mut running = spawn_child(&launcher, &task, "1500ms").unwrap();
+                    running.child.wait().unwrap();
+
+                    assert_eq!(
+                        launcher.launched(),
+                        vec![expected_command(model, proc_type, auto_loop)],
+                        "{:?} {:?} {:?}", model, proc_type, auto_loop
+                    );
+                }
+            }
+        }
+    }
+
+    /// Runs a video task through a mock launcher and returns its exit status
+    fn run_with_outcome(outcome: Outcome) -> ExitStatus {
+        let launcher = Arc::new(MockLauncher::new(outcome));
+        let playback = Playback::new(launcher.clone());
+        let task = Mutex::new(Task::new(Model::Pro, ProcType::Video, Autoloop::No, PathBuf::from("/media/film.mp4"), 5, String::new()));
+        run_task(&playback, &task, None).unwrap();
+
+        assert_eq!(launcher.launched().len(), 1);
+        playback.wait().unwrap()
+    }
+
+    #[test]
+    fn test_player_exits() {
+        assert_eq!(run_with_outcome(Outcome::Exit(3)).code(), Some(3));
+    }
+
+    #[test]
+    fn test_player_crashes() {
+        let status = run_with_outcome(Outcome::Crash);
+        assert!(!status.success());
+        assert_eq!(status.signal(), Some(11));
+    }
 
     // Test the seek second fn
     #[test]
     fn test_seek_seconds() {
-        // create start time from now, plus 30 seconds
-        let date_string = Local::now().checked_add_signed(TimeDelta::new(30, 0).unwrap()).unwrap();
-        let start_time = format!("{}", date_string.format("%H:%M:%S"));
-        let seek_seconds = get_seek_seconds(&start_time).unwrap();
-        assert_eq!(seek_seconds, String::from("0"));
-
-        // create start time from now, minus 30 seconds
-        let date_string = Local::now().checked_add_signed(TimeDelta::new(-30, 0).unwrap()).unwrap();
-        let start_time = format!("{}", date_string.format("%H:%M:%S"));
-        let seek_seconds = get_seek_seconds(&start_time).unwrap();
-        assert_eq!(seek_seconds, String::from("30"));
+        let now = timezone::now();
+
+        // a window starting in 30 seconds does not seek
+        let window_start = now.checked_add_signed(TimeDelta::new(30, 0).unwrap()).unwrap();
+        assert_eq!(get_seek_seconds(window_start, now), String::from("0"));
+
+        // a window that started 30 seconds ago seeks 30 seconds in
+        let window_start = now.checked_add_signed(TimeDelta::new(-30, 0).unwrap()).unwrap();
+        assert_eq!(get_seek_seconds(window_start, now), String::from("30000ms"));
     }
-}
 
+    #[test]
+    fn test_seek_seconds_across_midnight() {
+        let window_start = London.with_ymd_and_hms(2025, 6, 6, 23, 0, 0).single().unwrap();
+        let now = London.with_ymd_and_hms(2025, 6, 7, 0, 30, 0).single().unwrap();
+        assert_eq!(get_seek_seconds(window_start, now), String::from("5400000ms"));
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    io,
+    mem,
+    path::Path,
+    thread,
+    time::{
+        Duration,
+        Instant
+    },
+};
+
+use nix::libc;
+
+use crate::{
+    logi,
+    logw
+};
+use log::{
+    info,
+    warn
+};
+
+/// systemd-timesyncd creates this file once the clock has been synchronised
+const TIMESYNCD_FLAG: &str = "/run/systemd/timesync/synchronized";
+
+/// How often the synchronisation state is checked while waiting
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Asks the kernel whether the clock is synchronised. Whichever NTP client set the clock,
+/// timesyncd, chrony or ntpd, clears `STA_UNSYNC` through adjtimex once it has done so.
+fn kernel_synchronised() -> bool {
+    // with no modes set adjtimex only reads the clock state
+    // SAFETY: timex is plain data, for which all zeroes is a valid value
+    let mut timex: libc::timex = unsafe { mem::zeroed() };
+    // SAFETY: timex is valid and writable for the length of the call
+    if unsafe { libc::adjtimex(&mut timex) } == -1 {
+        logw!("Could not read the kernel clock state: {}", io::Error::last_os_error());
+        return false;
+    }
+    timex.status & libc::STA_UNSYNC == 0
+}
+
+/// Returns true if the system clock has been synchronised.
+/// The systemd-timesyncd flag file is checked first. If it is not present, the kernel's
+/// synchronised state is read through adjtimex, which also covers clocks set by chrony or
+/// ntpd.
+pub fn is_synchronised() -> bool {
+    Path::new(TIMESYNCD_FLAG).exists() || kernel_synchronised()
+}
+
+/// Blocks until the system clock is synchronised or the timeout passes.
+/// Returns whether the clock was synchronised.
+pub fn wait_for_sync(timeout: Duration) -> bool {
+    logi!("Waiting up to {}s for time synchronisation", timeout.as_secs());
+    let started = Instant::now();
+    loop {
+        if is_synchronised() {
+            logi!("System clock synchronised after {}s", started.elapsed().as_secs());
+            return true;
+        }
+        if started.elapsed() >= timeout {
+            logw!("System clock not synchronised after {}s, using the current time", timeout.as_secs());
+            return false;
+        }
+        crate::notify::alive();
+        thread::sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::OnceLock;
+
+use chrono::{
+    DateTime,
+    FixedOffset,
+    Local,
+    Utc
+};
+use chrono_tz::Tz;
+
+use crate::{
+    logi,
+    logw,
+    error::MediatimerError
+};
+use log::{
+    info,
+    warn
+};
+
+/// The timezone all scheduling is done in. Set once from `MT_TIMEZONE`, otherwise the
+/// operating system timezone is used.
+static TIMEZONE: OnceLock<Tz> = OnceLock::new();
+
+/// Parses an IANA timezone name such as "Europe/London"
+pub fn parse(name: &str) -> Result<Tz, MediatimerError> {
+    name.trim()
+        .parse::<Tz>()
+        .map_err(|e| MediatimerError::Config(format!("Timezone {} not recognised: {}", name, e)))
+}
+
+/// The timezone configured in the operating system, falling back to UTC if it cannot be read
+fn system_zone() -> Tz {
+    match iana_time_zone::get_timezone() {
+        Ok(name) => parse(&name).unwrap_or_else(|e| {
+            logw!("System timezone {} not recognised, using UTC: {}", name, e);
+            Tz::UTC
+        }),
+        Err(e) => {
+            logw!("System timezone could not be read, using UTC: {}", e);
+            Tz::UTC
+        }
+    }
+}
+
+/// Sets the installation timezone. This must be called before the timezone is first used.
+pub fn set(tz: Tz) {
+    if TIMEZONE.set(tz).is_err() {
+        logw!("Timezone already set, ignoring {}", tz);
+    } else {
+        logi!("Timezone set to {}", tz);
+    }
+}
+
+pub fn get() -> Tz {
+    *TIMEZONE.get_or_init(system_zone)
+}
+
+/// The current time in the installation timezone
+pub fn now() -> DateTime<Tz> {
+    Utc::now().with_timezone(&get())
+}
+
+/// The current time for log lines and file names. This uses the installation timezone once
+/// it is set, and local time before then, without fixing the timezone as `now` does. Logging
+/// before the config is read would otherwise stop `MT_TIMEZONE` from being applied.
+pub fn now_for_logs() -> DateTime<FixedOffset> {
+    match TIMEZONE.get() {
+        Some(tz) => Utc::now().with_timezone(tz).fixed_offset(),
+        None => Local::now().fixed_offset()
+    }
+}